@@ -1 +1,2 @@
-pub mod task;
\ No newline at end of file
+pub mod task;
+pub use task::{MaybeSend, spawn};
@@ -0,0 +1,39 @@
+#[cfg(feature = "dioxus")]
+use dioxus::prelude::spawn as dioxus_spawn;
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "dioxus")))]
+use tokio::spawn as tokio_spawn;
+
+/// 原生平台上等同于[Send]，wasm平台上不做要求
+/// 用于约束需要被[spawn]的future
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSend: Send {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send> MaybeSend for T {}
+
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSend {}
+#[cfg(target_arch = "wasm32")]
+impl<T> MaybeSend for T {}
+
+/// 在当前平台的异步运行时上启动任务，不关心其结果
+/// 启用feature: dioxus时使用dioxus的运行时，否则在原生平台上使用tokio
+pub fn spawn(f: impl Future<Output = ()> + MaybeSend + 'static) {
+    #[cfg(feature = "dioxus")]
+    {
+        dioxus_spawn(f);
+    }
+
+    #[cfg(all(not(target_arch = "wasm32"), not(feature = "dioxus")))]
+    {
+        tokio_spawn(f);
+    }
+
+    // wasm,但是又没有启用dioxus,当前没有可用的异步运行时
+    #[cfg(all(target_arch = "wasm32", not(feature = "dioxus")))]
+    {
+        let _ = f;
+        compile_error!(
+            "编译目标为:wasm32,但是未启用feature: dioxus, 这种情况下当前没有可用异步运行时"
+        );
+    }
+}
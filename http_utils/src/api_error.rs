@@ -0,0 +1,397 @@
+/// 接口错误与[Response]之间的转换
+/// - 服务端：处理函数返回[ApiResult]，成功时包装为`Response::success`，失败时包装为`Response::fail`
+/// - 客户端：[Response::into_result]/[decode]将响应解析为`Result<T, ApiError>`
+///
+/// `code`到http状态码的映射默认为[default_status_mapping]，可以按路由通过[StatusMappingLayer]配置，
+/// 如`router.layer(StatusMappingLayer::new(mapping))`，不同路由可以使用不同的映射
+/// 错误码可以用[crate::api_errors]定义为枚举
+///
+use crate::response::Response;
+use axum_core::response::IntoResponse;
+use http::StatusCode;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fmt::{Display, Formatter};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// 客户端解析响应失败时使用的错误码
+pub const DECODE_ERROR_CODE: i32 = -1;
+
+/// 默认的映射：`0`为200，合法的http状态码(100~599)原样使用，其余业务错误码为400
+pub fn default_status_mapping(code: i32) -> StatusCode {
+    match code {
+        0 => StatusCode::OK,
+        code => u16::try_from(code)
+            .ok()
+            .and_then(|code| StatusCode::from_u16(code).ok())
+            .unwrap_or(StatusCode::BAD_REQUEST),
+    }
+}
+
+/// 状态码由映射决定的响应，放在响应扩展中，由[StatusMappingLayer]按路由的映射替换状态码
+#[derive(Clone, Copy, Debug)]
+struct MappedCode(i32);
+
+/// 生成状态码由`code`映射得到的响应，先使用[default_status_mapping]
+pub(crate) fn mapped_response<T: Serialize>(
+    code: i32,
+    body: &T,
+    request_id_field: &'static str,
+) -> axum_core::response::Response {
+    let mut response = json_response(default_status_mapping(code), body, request_id_field);
+    response.extensions_mut().insert(MappedCode(code));
+    response
+}
+
+/// 按路由设置`code`到http状态码的映射，只影响内层由[Response]与[ApiError]生成的响应，
+/// 用[ApiError::with_status]指定了状态码的错误，以及[crate::problem::Problem](状态码同时写在响应体中)不受影响
+#[derive(Clone, Copy)]
+pub struct StatusMappingLayer {
+    mapping: fn(i32) -> StatusCode,
+}
+
+impl StatusMappingLayer {
+    pub fn new(mapping: fn(i32) -> StatusCode) -> Self {
+        StatusMappingLayer { mapping }
+    }
+}
+
+impl<S> tower_layer::Layer<S> for StatusMappingLayer {
+    type Service = StatusMappingService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        StatusMappingService {
+            inner,
+            mapping: self.mapping,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct StatusMappingService<S> {
+    inner: S,
+    mapping: fn(i32) -> StatusCode,
+}
+
+impl<S, Request, ResBody> tower_service::Service<Request> for StatusMappingService<S>
+where
+    S: tower_service::Service<Request, Response = http::Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = StatusMappingFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        StatusMappingFuture {
+            inner: self.inner.call(request),
+            mapping: self.mapping,
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct StatusMappingFuture<F> {
+    #[pin]
+    inner: F,
+    mapping: fn(i32) -> StatusCode,
+}
+
+impl<F, ResBody, E> Future for StatusMappingFuture<F>
+where
+    F: Future<Output = Result<http::Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match this.inner.poll(cx) {
+            Poll::Ready(Ok(mut response)) => {
+                // 取出标记，外层的映射不会再次替换
+                if let Some(MappedCode(code)) = response.extensions_mut().remove::<MappedCode>() {
+                    *response.status_mut() = (this.mapping)(code);
+                }
+                Poll::Ready(Ok(response))
+            }
+            ret => ret,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApiError {
+    pub code: i32,
+    pub message: Option<String>,
+    /// 指定的http状态码，为空时按映射获取，见[StatusMappingLayer]
+    pub status: Option<StatusCode>,
+}
+
+impl ApiError {
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        ApiError {
+            code,
+            message: Some(message.into()),
+            status: None,
+        }
+    }
+
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// 直接以http状态码作为错误码
+    pub fn from_status(status: StatusCode, message: impl Into<String>) -> Self {
+        ApiError::new(status.as_u16() as i32, message).with_status(status)
+    }
+
+    /// 指定的状态码，没有指定时为[default_status_mapping]的结果，不考虑路由的[StatusMappingLayer]
+    pub fn status(&self) -> StatusCode {
+        self.status
+            .unwrap_or_else(|| default_status_mapping(self.code))
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "{}: {}", self.code, message),
+            None => write!(f, "{}", self.code),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl<T> From<Response<T>> for ApiError {
+    fn from(value: Response<T>) -> Self {
+        ApiError {
+            code: value.code,
+            message: value.message,
+            status: None,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> axum_core::response::Response {
+        let body = Response::<()>::fail(self.code, self.message);
+        match self.status {
+            Some(status) => json_response(status, &body, REQUEST_ID_FIELD),
+            None => mapped_response(body.code, &body, REQUEST_ID_FIELD),
+        }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Response<T> {
+    fn into_response(self) -> axum_core::response::Response {
+        mapped_response(self.code, &self, REQUEST_ID_FIELD)
+    }
+}
+
+/// 处理函数的返回值，`Ok`包装为`Response::success`，`Err`包装为`Response::fail`
+/// ```ignore
+/// async fn handler() -> ApiResult<User> {
+///     find_user().await.map_err(|e| ApiError::new(1001, e.to_string())).into()
+/// }
+/// ```
+pub struct ApiResult<T>(pub Result<T, ApiError>);
+
+impl<T> ApiResult<T> {
+    pub fn ok(data: T) -> Self {
+        ApiResult(Ok(data))
+    }
+
+    pub fn err(error: ApiError) -> Self {
+        ApiResult(Err(error))
+    }
+}
+
+impl<T, E: Into<ApiError>> From<Result<T, E>> for ApiResult<T> {
+    fn from(value: Result<T, E>) -> Self {
+        ApiResult(value.map_err(Into::into))
+    }
+}
+
+impl<T: Serialize> IntoResponse for ApiResult<T> {
+    fn into_response(self) -> axum_core::response::Response {
+        match self.0 {
+            Ok(data) => Response::success(Some(data)).into_response(),
+            Err(e) => e.into_response(),
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Response<T> {
+    /// 转换为`Result<T, ApiError>`，成功但没有数据时按`null`解析(适用于`()`与`Option`)
+    pub fn into_result(self) -> Result<T, ApiError> {
+        if !self.is_success() {
+            return Err(self.into());
+        }
+        match self.data {
+            Some(data) => Ok(data),
+            None => T::deserialize(serde::de::value::UnitDeserializer::<serde_json::Error>::new())
+                .map_err(|_| ApiError::new(DECODE_ERROR_CODE, "missing data")),
+        }
+    }
+}
+
+/// 客户端解析响应体为`Result<T, ApiError>`
+pub fn decode<T: DeserializeOwned>(body: &[u8]) -> Result<T, ApiError> {
+    serde_json::from_slice::<Response<T>>(body)
+        .map_err(|e| ApiError::new(DECODE_ERROR_CODE, e.to_string()))?
+        .into_result()
+}
+
+/// 开启请求id注入时，响应体中请求id的字段名，见[crate::request_id::RequestIdLayer::inject_into_envelope]
+pub(crate) const REQUEST_ID_FIELD: &str = "request_id";
+
+/// 序列化为json响应，开启`http_request_id`时标记响应体可以由[crate::request_id::RequestIdLayer]
+/// 添加名为[request_id_field]的请求id字段
+pub(crate) fn json_response<T: Serialize>(
+    status: StatusCode,
+    body: &T,
+    request_id_field: &'static str,
+) -> axum_core::response::Response {
+    match serde_json::to_vec(body) {
+        Ok(body) => {
+            let body = bytes::Bytes::from(body);
+            let response = (
+                status,
+                [(http::header::CONTENT_TYPE, "application/json")],
+                body.clone(),
+            )
+                .into_response();
+            #[cfg(feature = "http_request_id")]
+            let response = crate::request_id::injectable(response, body, request_id_field);
+            #[cfg(not(feature = "http_request_id"))]
+            let _ = (body, request_id_field);
+            response
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// 检查错误码是否重复，返回第一个重复的错误码，可在测试中检查多个枚举之间的错误码
+pub const fn find_duplicate_code(codes: &[i32]) -> Option<i32> {
+    let mut i = 0;
+    while i < codes.len() {
+        let mut j = i + 1;
+        while j < codes.len() {
+            if codes[i] == codes[j] {
+                return Some(codes[i]);
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    None
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use axum_core::response::{IntoResponse, Response};
+    pub use http::StatusCode;
+}
+
+/// 定义错误码枚举，每个变体声明错误码，http状态码与默认信息，重复的错误码会导致编译失败
+/// ```ignore
+/// api_errors! {
+///     pub enum UserError {
+///         NameExists = (1001, CONFLICT, "name exists"),
+///         NotFound = (1002, NOT_FOUND, "user not found"),
+///     }
+/// }
+///
+/// async fn handler() -> ApiResult<User> {
+///     ApiResult::err(UserError::NotFound.into())
+/// }
+/// ```
+/// 生成的枚举可以转换为[ApiError]与[crate::problem::Problem]，也可以直接作为响应返回，都会带上定义的http状态码
+/// 不提供到[Response]的转换，[Response]没有状态码，会丢失定义的状态码
+#[macro_export]
+macro_rules! api_errors {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident = ($code:expr, $status:ident, $message:expr)
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant,
+            )*
+        }
+
+        impl $name {
+            /// 所有的错误码
+            pub const CODES: &'static [i32] = &[$($code),*];
+
+            pub const fn code(&self) -> i32 {
+                match self {
+                    $($name::$variant => $code,)*
+                }
+            }
+
+            pub fn status(&self) -> $crate::api_error::__private::StatusCode {
+                match self {
+                    $($name::$variant => $crate::api_error::__private::StatusCode::$status,)*
+                }
+            }
+
+            pub const fn message(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $message,)*
+                }
+            }
+
+            /// 替换默认信息
+            pub fn with_message(&self, message: impl Into<String>) -> $crate::api_error::ApiError {
+                $crate::api_error::ApiError::new(self.code(), message).with_status(self.status())
+            }
+        }
+
+        const _: () = assert!(
+            $crate::api_error::find_duplicate_code($name::CODES).is_none(),
+            concat!("duplicate error code in ", stringify!($name))
+        );
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                write!(f, "{}: {}", self.code(), self.message())
+            }
+        }
+
+        impl ::std::error::Error for $name {}
+
+        impl From<$name> for $crate::api_error::ApiError {
+            fn from(value: $name) -> Self {
+                value.with_message(value.message())
+            }
+        }
+
+        impl From<$name> for $crate::problem::Problem {
+            fn from(value: $name) -> Self {
+                $crate::api_error::ApiError::from(value).into()
+            }
+        }
+
+        impl $crate::api_error::__private::IntoResponse for $name {
+            fn into_response(self) -> $crate::api_error::__private::Response {
+                $crate::api_error::__private::IntoResponse::into_response(
+                    $crate::api_error::ApiError::from(self),
+                )
+            }
+        }
+    };
+}
@@ -0,0 +1,491 @@
+/// 响应字段的命名风格，在运行时选择，同一个服务可以同时兼容不同的客户端
+/// - 按请求：请求头[FIELD_CASING_HEADER]，值为`camel`/`camelCase`或`snake`/`snake_case`
+/// - 按路由：`router.layer(Extension(FieldCasing::Camel))`
+/// - 都没有时使用[FieldCasing::default]，开启`http_serde_camel_case`时为camelCase，否则为snake_case
+///
+/// 处理函数通过[FieldCasing]提取器获取命名风格，再用[Cased]包装响应，如`casing.wrap(ApiResult::ok(user))`
+/// 只转换结构体的字段名(包括`data`中嵌套的结构体)，map的键(如`HashMap`，`serde_json::Value`中的键)属于数据，保持不变；
+/// `#[serde(flatten)]`的结构体按map序列化，其字段名也不转换
+/// 结构体字段名按Rust的snake_case命名，只有全部由小写字母，数字与`_`组成的字段名会被转换，
+/// 带有大写字母的字段名(如`#[serde(rename = "HTTPStatus")]`，`#[serde(rename_all = "camelCase")]`)视为已经指定了名称，保持不变；
+/// 重命名为snake_case的字段无法与未重命名的字段区分，仍会被转换
+/// 按请求选择风格的响应带有`Vary: x-field-casing`，避免缓存混用不同风格的响应
+///
+use crate::api_error::{ApiError, ApiResult, REQUEST_ID_FIELD, mapped_response};
+use crate::response::Response;
+use axum_core::extract::FromRequestParts;
+use axum_core::response::IntoResponse;
+use http::request::Parts;
+use serde::ser::{
+    SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+    SerializeTupleStruct, SerializeTupleVariant,
+};
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::convert::Infallible;
+
+pub const FIELD_CASING_HEADER: &str = "x-field-casing";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FieldCasing {
+    Snake,
+    Camel,
+}
+
+impl Default for FieldCasing {
+    fn default() -> Self {
+        if cfg!(feature = "http_serde_camel_case") {
+            FieldCasing::Camel
+        } else {
+            FieldCasing::Snake
+        }
+    }
+}
+
+impl FieldCasing {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            v if v.eq_ignore_ascii_case("camel") || v.eq_ignore_ascii_case("camelCase") => {
+                Some(FieldCasing::Camel)
+            }
+            v if v.eq_ignore_ascii_case("snake") || v.eq_ignore_ascii_case("snake_case") => {
+                Some(FieldCasing::Snake)
+            }
+            _ => None,
+        }
+    }
+
+    /// 转换单个键，已经是目标风格的键保持不变
+    /// 转换为snake_case时连续的大写字母视为一个单词，如`HTTPStatus`转换为`http_status`，`userID`转换为`user_id`
+    pub fn convert_key(&self, key: &str) -> String {
+        match self {
+            FieldCasing::Snake => {
+                let chars: Vec<char> = key.chars().collect();
+                let mut ret = String::with_capacity(key.len() + 4);
+                for (i, c) in chars.iter().enumerate() {
+                    if c.is_ascii_uppercase() {
+                        let prev = i.checked_sub(1).map(|i| chars[i]);
+                        let next = chars.get(i + 1);
+                        // 单词的开头：前一个是小写字母或数字，或者是连续大写字母中的最后一个(后面跟着小写字母)
+                        let word_start = prev.is_some_and(|prev| {
+                            prev.is_ascii_lowercase()
+                                || prev.is_ascii_digit()
+                                || (prev.is_ascii_uppercase()
+                                    && next.is_some_and(|next| next.is_ascii_lowercase()))
+                        });
+                        if word_start && !ret.ends_with('_') {
+                            ret.push('_');
+                        }
+                        ret.push(c.to_ascii_lowercase());
+                    } else {
+                        ret.push(*c);
+                    }
+                }
+                ret
+            }
+            FieldCasing::Camel => {
+                let mut ret = String::with_capacity(key.len());
+                let mut upper = false;
+                for c in key.chars() {
+                    if c == '_' && !ret.is_empty() {
+                        upper = true;
+                    } else if upper {
+                        ret.push(c.to_ascii_uppercase());
+                        upper = false;
+                    } else {
+                        ret.push(c);
+                    }
+                }
+                ret
+            }
+        }
+    }
+
+    /// 转换结构体的字段名，只转换snake_case的字段名，其他字段名视为已经重命名，保持不变
+    fn convert_field(&self, key: &'static str) -> Cow<'static, str> {
+        let is_snake = key
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_');
+        if !is_snake {
+            return Cow::Borrowed(key);
+        }
+        match self {
+            FieldCasing::Snake => Cow::Borrowed(key),
+            FieldCasing::Camel => Cow::Owned(self.convert_key(key)),
+        }
+    }
+
+    /// 响应体中请求id的字段名
+    fn request_id_field(&self) -> &'static str {
+        match self {
+            FieldCasing::Snake => REQUEST_ID_FIELD,
+            FieldCasing::Camel => "requestId",
+        }
+    }
+
+    /// 递归转换json中所有对象的键，包括map的键，只需要转换结构体字段名时使用[FieldCasing::to_value]
+    pub fn apply(&self, value: Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| (self.convert_key(&key), self.apply(value)))
+                    .collect::<Map<String, Value>>(),
+            ),
+            Value::Array(values) => {
+                Value::Array(values.into_iter().map(|value| self.apply(value)).collect())
+            }
+            value => value,
+        }
+    }
+
+    /// 序列化并转换结构体的字段名，map的键保持不变
+    pub fn to_value<T: Serialize>(&self, value: &T) -> Result<Value, serde_json::Error> {
+        serde_json::to_value(CasedValue(*self, value))
+    }
+
+    pub fn wrap<T>(self, body: T) -> Cased<T> {
+        Cased(self, body)
+    }
+}
+
+impl<State: Send + Sync> FromRequestParts<State> for FieldCasing {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &State) -> Result<Self, Self::Rejection> {
+        let from_header = parts
+            .headers
+            .get(FIELD_CASING_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(FieldCasing::parse);
+        Ok(from_header
+            .or_else(|| parts.extensions.get::<FieldCasing>().copied())
+            .unwrap_or_default())
+    }
+}
+
+/// 按指定的命名风格序列化的响应
+pub struct Cased<T>(pub FieldCasing, pub T);
+
+impl<T: Serialize> IntoResponse for Cased<Response<T>> {
+    fn into_response(self) -> axum_core::response::Response {
+        let Cased(casing, body) = self;
+        let mut response = match casing.to_value(&body) {
+            Ok(value) => mapped_response(body.code, &value, casing.request_id_field()),
+            Err(e) => (http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        };
+        response.headers_mut().append(
+            http::header::VARY,
+            http::HeaderValue::from_static(FIELD_CASING_HEADER),
+        );
+        response
+    }
+}
+
+impl IntoResponse for Cased<ApiError> {
+    fn into_response(self) -> axum_core::response::Response {
+        // 错误响应没有data，字段名与风格无关
+        self.1.into_response()
+    }
+}
+
+impl<T: Serialize> IntoResponse for Cased<ApiResult<T>> {
+    fn into_response(self) -> axum_core::response::Response {
+        let Cased(casing, ApiResult(result)) = self;
+        match result {
+            Ok(data) => Cased(casing, Response::success(Some(data))).into_response(),
+            Err(e) => e.into_response(),
+        }
+    }
+}
+
+/// 按命名风格转换结构体字段名的序列化包装
+pub struct CasedValue<'a, T: ?Sized>(pub FieldCasing, pub &'a T);
+
+impl<T: ?Sized + Serialize> Serialize for CasedValue<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.1.serialize(CasedSerializer {
+            casing: self.0,
+            inner: serializer,
+        })
+    }
+}
+
+/// 转发给内部的[Serializer]，只替换结构体的字段名，嵌套的值继续用[CasedValue]包装
+struct CasedSerializer<S> {
+    casing: FieldCasing,
+    inner: S,
+}
+
+/// 序列化中的复合类型，元素用[CasedValue]包装
+/// 结构体按map序列化，转换后的字段名不需要是`'static`的
+struct CasedCompound<C> {
+    casing: FieldCasing,
+    inner: C,
+}
+
+impl<C> CasedCompound<C> {
+    fn new(casing: FieldCasing, inner: C) -> Self {
+        CasedCompound { casing, inner }
+    }
+}
+
+macro_rules! forward_serialize {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {$(
+        fn $method(self, $($arg: $ty),*) -> Result<Self::Ok, Self::Error> {
+            self.inner.$method($($arg),*)
+        }
+    )*};
+}
+
+impl<S: Serializer> Serializer for CasedSerializer<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = CasedCompound<S::SerializeSeq>;
+    type SerializeTuple = CasedCompound<S::SerializeTuple>;
+    type SerializeTupleStruct = CasedCompound<S::SerializeTupleStruct>;
+    type SerializeTupleVariant = CasedCompound<S::SerializeTupleVariant>;
+    type SerializeMap = CasedCompound<S::SerializeMap>;
+    type SerializeStruct = CasedCompound<S::SerializeMap>;
+    type SerializeStructVariant = CasedStructVariant<S>;
+
+    forward_serialize! {
+        serialize_bool(v: bool);
+        serialize_i8(v: i8);
+        serialize_i16(v: i16);
+        serialize_i32(v: i32);
+        serialize_i64(v: i64);
+        serialize_i128(v: i128);
+        serialize_u8(v: u8);
+        serialize_u16(v: u16);
+        serialize_u32(v: u32);
+        serialize_u64(v: u64);
+        serialize_u128(v: u128);
+        serialize_f32(v: f32);
+        serialize_f64(v: f64);
+        serialize_char(v: char);
+        serialize_str(v: &str);
+        serialize_bytes(v: &[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(name: &'static str);
+        serialize_unit_variant(name: &'static str, index: u32, variant: &'static str);
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_some(&CasedValue(self.casing, value))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.inner
+            .serialize_newtype_struct(name, &CasedValue(self.casing, value))
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.inner
+            .serialize_newtype_variant(name, index, variant, &CasedValue(self.casing, value))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(CasedCompound::new(self.casing, self.inner.serialize_seq(len)?))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Ok(CasedCompound::new(self.casing, self.inner.serialize_tuple(len)?))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        let inner = self.inner.serialize_tuple_struct(name, len)?;
+        Ok(CasedCompound::new(self.casing, inner))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        let inner = self
+            .inner
+            .serialize_tuple_variant(name, index, variant, len)?;
+        Ok(CasedCompound::new(self.casing, inner))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(CasedCompound::new(self.casing, self.inner.serialize_map(len)?))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(CasedCompound::new(self.casing, self.inner.serialize_map(Some(len))?))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(CasedStructVariant {
+            casing: self.casing,
+            inner: self.inner,
+            name,
+            index,
+            variant,
+            fields: Vec::with_capacity(len),
+        })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+impl<C: SerializeSeq> SerializeSeq for CasedCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.inner.serialize_element(&CasedValue(self.casing, value))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeTuple> SerializeTuple for CasedCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.inner.serialize_element(&CasedValue(self.casing, value))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeTupleStruct> SerializeTupleStruct for CasedCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.inner.serialize_field(&CasedValue(self.casing, value))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeTupleVariant> SerializeTupleVariant for CasedCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.inner.serialize_field(&CasedValue(self.casing, value))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeMap> SerializeMap for CasedCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    /// map的键是数据，不转换
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Self::Error> {
+        self.inner.serialize_key(key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.inner.serialize_value(&CasedValue(self.casing, value))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeMap> SerializeStruct for CasedCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        let key = self.casing.convert_field(key);
+        self.inner.serialize_entry(&key, &CasedValue(self.casing, value))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.end()
+    }
+}
+
+/// 结构体变体的字段先转换为json，结束时作为newtype变体的值序列化，以便使用转换后的字段名
+struct CasedStructVariant<S> {
+    casing: FieldCasing,
+    inner: S,
+    name: &'static str,
+    index: u32,
+    variant: &'static str,
+    /// 保持字段的顺序
+    fields: Vec<(String, Value)>,
+}
+
+impl<S: Serializer> SerializeStructVariant for CasedStructVariant<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        let value = serde_json::to_value(CasedValue(self.casing, value))
+            .map_err(serde::ser::Error::custom)?;
+        self.fields
+            .push((self.casing.convert_field(key).into_owned(), value));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        let fields = FieldsMap(&self.fields);
+        self.inner
+            .serialize_newtype_variant(self.name, self.index, self.variant, &fields)
+    }
+}
+
+struct FieldsMap<'a>(&'a [(String, Value)]);
+
+impl Serialize for FieldsMap<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(key, value)| (key, value)))
+    }
+}
@@ -0,0 +1,179 @@
+/// 调用返回[Response]的接口的客户端，原生平台使用reqwest(hyper)，wasm32上reqwest使用浏览器的fetch
+/// - 设置令牌后每个请求都带上`Authorization: Bearer`，克隆的客户端共享令牌，登录后更新即可
+/// - 响应解析为`Result<T, ApiError>`，非2xx的响应依次尝试按[Response]，[Problem]解析，
+///   都不是时以http状态码作为错误码，响应体作为错误信息
+/// - 超时使用`timer`，包括读取响应体的时间
+///
+/// 请求失败时的错误码见[NETWORK_ERROR_CODE]，[TIMEOUT_ERROR_CODE]与[DECODE_ERROR_CODE]
+///
+use crate::api_error::{ApiError, DECODE_ERROR_CODE};
+use crate::problem::{PROBLEM_JSON, Problem};
+use crate::response::Response;
+use http::{Method, StatusCode};
+use serde::Serialize;
+use serde::de::{DeserializeOwned, IgnoredAny};
+use std::pin::pin;
+use std::sync::{Arc, RwLock};
+use std::task::Poll;
+use types::{Duration, Instant};
+
+/// 无法发送请求或读取响应失败
+pub const NETWORK_ERROR_CODE: i32 = -2;
+
+/// 请求超时
+pub const TIMEOUT_ERROR_CODE: i32 = -3;
+
+#[derive(Clone)]
+pub struct ApiClient {
+    base_url: String,
+    client: reqwest::Client,
+    token: Arc<RwLock<Option<String>>>,
+    timeout: Option<Duration>,
+}
+
+impl ApiClient {
+    /// [base_url]如`http://127.0.0.1:8080/api`，请求的路径拼接在后面
+    pub fn new(base_url: impl Into<String>) -> Self {
+        ApiClient {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+            token: Arc::new(RwLock::new(None)),
+            timeout: None,
+        }
+    }
+
+    /// 使用自定义的reqwest客户端，如开启了tls或设置了代理
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn with_token(self, token: impl Into<String>) -> Self {
+        self.set_token(Some(token.into()));
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// 更新令牌，对所有克隆的客户端生效
+    pub fn set_token(&self, token: Option<String>) {
+        *self.token.write().unwrap() = token;
+    }
+
+    pub fn token(&self) -> Option<String> {
+        self.token.read().unwrap().clone()
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    /// 创建请求，已经带上令牌，可以继续设置查询参数等，再用[ApiClient::send]发送
+    pub fn request(&self, method: Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, self.url(path));
+        match self.token() {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ApiError> {
+        self.send(self.request(Method::GET, path)).await
+    }
+
+    pub async fn delete<T: DeserializeOwned>(&self, path: &str) -> Result<T, ApiError> {
+        self.send(self.request(Method::DELETE, path)).await
+    }
+
+    pub async fn post<B: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, ApiError> {
+        self.send(self.request(Method::POST, path).json(body)).await
+    }
+
+    pub async fn put<B: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, ApiError> {
+        self.send(self.request(Method::PUT, path).json(body)).await
+    }
+
+    /// 发送请求并解析响应
+    pub async fn send<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, ApiError> {
+        let future = async {
+            let response = request.send().await.map_err(network_error)?;
+            let status = response.status();
+            let is_problem = response
+                .headers()
+                .get(http::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.starts_with(PROBLEM_JSON));
+            let body = response.bytes().await.map_err(network_error)?;
+            decode_response(status, is_problem, &body)
+        };
+        match self.timeout {
+            Some(timeout) => with_timeout(timeout, future).await?,
+            None => future.await,
+        }
+    }
+}
+
+fn network_error(e: reqwest::Error) -> ApiError {
+    ApiError::new(NETWORK_ERROR_CODE, e.to_string())
+}
+
+/// 按http状态码与响应体解析
+pub fn decode_response<T: DeserializeOwned>(
+    status: StatusCode,
+    is_problem: bool,
+    body: &[u8],
+) -> Result<T, ApiError> {
+    if status.is_success() {
+        return serde_json::from_slice::<Response<T>>(body)
+            .map_err(|e| ApiError::new(DECODE_ERROR_CODE, e.to_string()).with_status(status))?
+            .into_result();
+    }
+    if is_problem && let Ok(problem) = serde_json::from_slice::<Problem>(body) {
+        return Err(problem.into());
+    }
+    // 错误响应中的data与T无关
+    match serde_json::from_slice::<Response<IgnoredAny>>(body) {
+        Ok(response) if !response.is_success() => Err(ApiError::from(response).with_status(status)),
+        _ => {
+            let message = String::from_utf8_lossy(body).trim().to_string();
+            let message = if message.is_empty() {
+                status.canonical_reason().unwrap_or_default().to_string()
+            } else {
+                message
+            };
+            Err(ApiError::from_status(status, message))
+        }
+    }
+}
+
+async fn with_timeout<F: Future>(timeout: Duration, future: F) -> Result<F::Output, ApiError> {
+    let mut future = pin!(future);
+    let mut sleep = pin!(timer::timer::sleep_until(Instant::now() + timeout));
+    std::future::poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        match sleep.as_mut().poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(ApiError::new(
+                TIMEOUT_ERROR_CODE,
+                format!("request timed out after {timeout:?}"),
+            ))),
+            Poll::Pending => Poll::Pending,
+        }
+    })
+    .await
+}
@@ -0,0 +1,237 @@
+/// 获取客户端的真实ip
+/// 代理链依次从`Forwarded`(RFC 7239)或`X-Forwarded-For`中获取，再加上直连的地址([ConnectInfo])，
+/// 从右往左跳过可信代理([TrustedProxies])，第一个不可信的地址即为客户端地址；
+/// 没有代理头而直连地址可信时使用`X-Real-IP`
+///
+/// 可信代理通过请求扩展配置，如`router.layer(Extension(TrustedProxies::parse(["10.0.0.0/8"])?))`，
+/// 未配置时不信任任何代理头，只使用直连地址
+///
+use axum::extract::ConnectInfo;
+use axum_core::extract::FromRequestParts;
+use axum_core::response::IntoResponse;
+use http::StatusCode;
+use http::request::Parts;
+use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+/// ip地址段，如`10.0.0.0/8`，`::1/128`，不带前缀长度时为单个地址
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IpCidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpCidr {
+    pub fn new(addr: IpAddr, prefix: u8) -> Result<Self, InvalidCidr> {
+        let max = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix > max {
+            return Err(InvalidCidr(format!("{addr}/{prefix}")));
+        }
+        Ok(IpCidr { addr, prefix })
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, to_canonical(*ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                prefix_eq(u32::from(net) as u128, u32::from(ip) as u128, 32, self.prefix)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_eq(u128::from(net), u128::from(ip), 128, self.prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_eq(a: u128, b: u128, bits: u8, prefix: u8) -> bool {
+    if prefix == 0 {
+        return true;
+    }
+    let shift = bits - prefix;
+    (a >> shift) == (b >> shift)
+}
+
+/// ipv4映射的ipv6地址按ipv4处理
+fn to_canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        ip => ip,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidCidr(pub String);
+
+impl Display for InvalidCidr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid cidr: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidCidr {}
+
+impl FromStr for IpCidr {
+    type Err = InvalidCidr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidCidr(s.to_string());
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s.trim(), None),
+        };
+        let addr = to_canonical(IpAddr::from_str(addr).map_err(|_| invalid())?);
+        let prefix = match (prefix, addr) {
+            (Some(prefix), _) => prefix.parse::<u8>().map_err(|_| invalid())?,
+            (None, IpAddr::V4(_)) => 32,
+            (None, IpAddr::V6(_)) => 128,
+        };
+        IpCidr::new(addr, prefix).map_err(|_| invalid())
+    }
+}
+
+/// 可信的代理地址段
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrustedProxies(Vec<IpCidr>);
+
+impl TrustedProxies {
+    pub fn new(cidrs: Vec<IpCidr>) -> Self {
+        TrustedProxies(cidrs)
+    }
+
+    pub fn parse<S: AsRef<str>>(cidrs: impl IntoIterator<Item = S>) -> Result<Self, InvalidCidr> {
+        cidrs
+            .into_iter()
+            .map(|cidr| cidr.as_ref().parse())
+            .collect::<Result<Vec<_>, _>>()
+            .map(TrustedProxies)
+    }
+
+    pub fn is_trusted(&self, ip: &IpAddr) -> bool {
+        self.0.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    /// 按可信代理解析客户端地址，直连地址未知时返回`None`
+    pub fn resolve(&self, parts: &Parts) -> Option<IpAddr> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|info| to_canonical(info.0.ip()))?;
+        if !self.is_trusted(&peer) {
+            return Some(peer);
+        }
+        let hops = forwarded_hops(parts).or_else(|| x_forwarded_for_hops(parts));
+        let Some(hops) = hops else {
+            return header(parts, "x-real-ip")
+                .and_then(|ip| parse_node(&ip))
+                .or(Some(peer));
+        };
+        // 从右往左找到第一个不可信的地址，无法解析的地址之后的内容不可信
+        let mut client = peer;
+        for hop in hops.iter().rev() {
+            match hop {
+                Some(ip) => {
+                    client = *ip;
+                    if !self.is_trusted(ip) {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+        Some(client)
+    }
+}
+
+fn header(parts: &Parts, name: &str) -> Option<String> {
+    let values: Vec<&str> = parts
+        .headers
+        .get_all(name)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .collect();
+    let value = values.join(",");
+    (!value.trim().is_empty()).then_some(value)
+}
+
+/// `Forwarded`中每一项的`for`参数
+fn forwarded_hops(parts: &Parts) -> Option<Vec<Option<IpAddr>>> {
+    let value = header(parts, "forwarded")?;
+    Some(
+        value
+            .split(',')
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(key, _)| key.trim().eq_ignore_ascii_case("for"))
+                    .and_then(|(_, node)| parse_node(node))
+            })
+            .collect(),
+    )
+}
+
+fn x_forwarded_for_hops(parts: &Parts) -> Option<Vec<Option<IpAddr>>> {
+    let value = header(parts, "x-forwarded-for")?;
+    Some(value.split(',').map(parse_node).collect())
+}
+
+/// 解析节点地址：`192.0.2.1`，`192.0.2.1:80`，`"[2001:db8::1]:4711"`，`2001:db8::1`
+/// `unknown`与混淆的标识(`_hidden`)返回`None`
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    if let Some(rest) = node.strip_prefix('[') {
+        let (ip, _) = rest.split_once(']')?;
+        return ip.parse::<IpAddr>().ok().map(to_canonical);
+    }
+    node.parse::<IpAddr>()
+        .or_else(|_| node.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .ok()
+        .map(to_canonical)
+}
+
+/// 客户端的真实ip，见模块说明
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ClientIp(pub IpAddr);
+
+impl ClientIp {
+    /// 使用请求扩展中的[TrustedProxies]解析，未配置时只使用直连地址
+    pub fn from_parts(parts: &Parts) -> Option<Self> {
+        match parts.extensions.get::<TrustedProxies>() {
+            Some(trusted) => trusted.resolve(parts),
+            None => TrustedProxies::default().resolve(parts),
+        }
+        .map(ClientIp)
+    }
+}
+
+impl Display for ClientIp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// 无法获取直连地址，通常是没有使用`into_make_service_with_connect_info`启动服务
+#[derive(Debug)]
+pub struct ClientIpRejection;
+
+impl IntoResponse for ClientIpRejection {
+    fn into_response(self) -> axum_core::response::Response {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "missing connect info, serve with `into_make_service_with_connect_info`",
+        )
+            .into_response()
+    }
+}
+
+impl<State: Send + Sync> FromRequestParts<State> for ClientIp {
+    type Rejection = ClientIpRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &State) -> Result<Self, Self::Rejection> {
+        ClientIp::from_parts(parts).ok_or(ClientIpRejection)
+    }
+}
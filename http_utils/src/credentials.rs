@@ -0,0 +1,238 @@
+/// 内部管理接口与旧系统对接使用的简单凭据
+/// - [BasicAuth]：`Authorization: Basic`，缺少时的响应带有`WWW-Authenticate`，浏览器会弹出登录框
+/// - [ApiKeyAuth]：自定义请求头中的API key，默认为[API_KEY_HEADER]，
+///   可以按路由配置，如`router.layer(Extension(ApiKeyHeader(HeaderName::from_static("x-legacy-key"))))`
+///
+/// 提取器只负责读取凭据，处理函数用`verify`在常数时间内与预期的值比较，
+/// 拒绝时统一返回401的[crate::response::Response]
+///
+use crate::api_error::ApiError;
+use axum_core::extract::FromRequestParts;
+use axum_core::response::IntoResponse;
+use axum_extra::headers::authorization::Basic;
+use axum_extra::headers::{Authorization, HeaderMapExt};
+use http::request::Parts;
+use http::{HeaderName, HeaderValue, StatusCode};
+use sha2::{Digest, Sha256};
+use std::fmt::{Display, Formatter};
+use subtle::ConstantTimeEq;
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Basic认证缺少凭据时的质询
+pub const BASIC_CHALLENGE: &str = r#"Basic realm="restricted", charset="UTF-8""#;
+
+/// 常数时间比较，先取哈希再比较，不会泄露长度
+pub fn constant_time_eq(a: impl AsRef<[u8]>, b: impl AsRef<[u8]>) -> bool {
+    let a = Sha256::digest(a.as_ref());
+    let b = Sha256::digest(b.as_ref());
+    a.ct_eq(&b).into()
+}
+
+/// 与多个值比较，总是比较所有的值，用于密钥轮换期间同时接受新旧的key
+pub fn constant_time_eq_any<T: AsRef<[u8]>>(
+    value: impl AsRef<[u8]>,
+    expected: impl IntoIterator<Item = T>,
+) -> bool {
+    expected
+        .into_iter()
+        .fold(false, |matched, expected| {
+            constant_time_eq(value.as_ref(), expected) | matched
+        })
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BasicAuthRejection {
+    Missing,
+    /// 不是Basic或base64解码失败
+    Malformed,
+    /// 用户名或密码不正确
+    Invalid,
+}
+
+impl Display for BasicAuthRejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BasicAuthRejection::Missing => write!(f, "basic credentials missing"),
+            BasicAuthRejection::Malformed => write!(f, "malformed basic credentials"),
+            BasicAuthRejection::Invalid => write!(f, "invalid username or password"),
+        }
+    }
+}
+
+impl std::error::Error for BasicAuthRejection {}
+
+impl From<BasicAuthRejection> for ApiError {
+    fn from(value: BasicAuthRejection) -> Self {
+        ApiError::from_status(StatusCode::UNAUTHORIZED, value.to_string())
+    }
+}
+
+impl IntoResponse for BasicAuthRejection {
+    fn into_response(self) -> axum_core::response::Response {
+        let mut response = ApiError::from(self).into_response();
+        response.headers_mut().insert(
+            http::header::WWW_AUTHENTICATE,
+            HeaderValue::from_static(BASIC_CHALLENGE),
+        );
+        response
+    }
+}
+
+/// `Authorization: Basic`中的用户名与密码
+#[derive(Clone, PartialEq, Eq)]
+pub struct BasicAuth {
+    pub username: String,
+    pub password: String,
+}
+
+impl std::fmt::Debug for BasicAuth {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BasicAuth")
+            .field("username", &self.username)
+            .field("password", &"***")
+            .finish()
+    }
+}
+
+impl BasicAuth {
+    pub fn from_parts(parts: &Parts) -> Result<Self, BasicAuthRejection> {
+        let Authorization(basic) = parts
+            .headers
+            .typed_try_get::<Authorization<Basic>>()
+            .map_err(|_| BasicAuthRejection::Malformed)?
+            .ok_or(BasicAuthRejection::Missing)?;
+        Ok(BasicAuth {
+            username: basic.username().to_string(),
+            password: basic.password().to_string(),
+        })
+    }
+
+    /// 用户名与密码都会比较，不会因为用户名不匹配而提前返回
+    pub fn verify(&self, username: &str, password: &str) -> Result<(), BasicAuthRejection> {
+        let matched = constant_time_eq(&self.username, username)
+            & constant_time_eq(&self.password, password);
+        if matched {
+            Ok(())
+        } else {
+            Err(BasicAuthRejection::Invalid)
+        }
+    }
+}
+
+impl<State: Send + Sync> FromRequestParts<State> for BasicAuth {
+    type Rejection = BasicAuthRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &State) -> Result<Self, Self::Rejection> {
+        BasicAuth::from_parts(parts)
+    }
+}
+
+/// 读取API key的请求头，通过请求扩展配置
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiKeyHeader(pub HeaderName);
+
+impl Default for ApiKeyHeader {
+    fn default() -> Self {
+        ApiKeyHeader(HeaderName::from_static(API_KEY_HEADER))
+    }
+}
+
+/// 拒绝时带有读取的请求头
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ApiKeyRejection {
+    Missing(HeaderName),
+    /// 不是可见的ascii字符
+    Malformed(HeaderName),
+    Invalid(HeaderName),
+}
+
+impl Display for ApiKeyRejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiKeyRejection::Missing(header) => write!(f, "api key missing in header `{header}`"),
+            ApiKeyRejection::Malformed(header) => {
+                write!(f, "malformed api key in header `{header}`")
+            }
+            ApiKeyRejection::Invalid(header) => write!(f, "invalid api key in header `{header}`"),
+        }
+    }
+}
+
+impl std::error::Error for ApiKeyRejection {}
+
+impl From<ApiKeyRejection> for ApiError {
+    fn from(value: ApiKeyRejection) -> Self {
+        ApiError::from_status(StatusCode::UNAUTHORIZED, value.to_string())
+    }
+}
+
+impl IntoResponse for ApiKeyRejection {
+    fn into_response(self) -> axum_core::response::Response {
+        ApiError::from(self).into_response()
+    }
+}
+
+/// 请求头中的API key
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiKeyAuth {
+    pub header: HeaderName,
+    pub key: String,
+}
+
+impl std::fmt::Debug for ApiKeyAuth {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiKeyAuth")
+            .field("header", &self.header)
+            .field("key", &"***")
+            .finish()
+    }
+}
+
+impl ApiKeyAuth {
+    /// 使用请求扩展中的[ApiKeyHeader]，未配置时为[API_KEY_HEADER]
+    pub fn from_parts(parts: &Parts) -> Result<Self, ApiKeyRejection> {
+        let header = parts
+            .extensions
+            .get::<ApiKeyHeader>()
+            .cloned()
+            .unwrap_or_default()
+            .0;
+        let key = match parts.headers.get(&header) {
+            Some(value) => value
+                .to_str()
+                .map_err(|_| ApiKeyRejection::Malformed(header.clone()))?
+                .trim()
+                .to_string(),
+            None => return Err(ApiKeyRejection::Missing(header)),
+        };
+        if key.is_empty() {
+            return Err(ApiKeyRejection::Missing(header));
+        }
+        Ok(ApiKeyAuth { header, key })
+    }
+
+    pub fn verify(&self, expected: &str) -> Result<(), ApiKeyRejection> {
+        self.verify_any([expected])
+    }
+
+    /// 与任意一个匹配即可
+    pub fn verify_any<T: AsRef<[u8]>>(
+        &self,
+        expected: impl IntoIterator<Item = T>,
+    ) -> Result<(), ApiKeyRejection> {
+        if constant_time_eq_any(&self.key, expected) {
+            Ok(())
+        } else {
+            Err(ApiKeyRejection::Invalid(self.header.clone()))
+        }
+    }
+}
+
+impl<State: Send + Sync> FromRequestParts<State> for ApiKeyAuth {
+    type Rejection = ApiKeyRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &State) -> Result<Self, Self::Rejection> {
+        ApiKeyAuth::from_parts(parts)
+    }
+}
@@ -1,2 +1,667 @@
+#[cfg(feature = "http_types")]
+pub mod api_error;
+#[cfg(feature = "http_types")]
+pub mod casing;
+#[cfg(feature = "http_client")]
+pub mod client;
+#[cfg(feature = "http_client_ip")]
+pub mod client_ip;
+#[cfg(feature = "http_credentials")]
+pub mod credentials;
+#[cfg(feature = "http_page")]
+pub mod page;
+#[cfg(feature = "http_types")]
+pub mod problem;
+#[cfg(feature = "http_request_id")]
+pub mod request_id;
 pub mod response;
+pub mod token_source;
 pub mod utils;
+
+#[cfg(all(test, feature = "http_types"))]
+mod test {
+    use crate::api_error::{
+        ApiError, ApiResult, DECODE_ERROR_CODE, StatusMappingLayer, decode,
+        default_status_mapping, find_duplicate_code,
+    };
+    use crate::response::Response;
+    use axum_core::response::IntoResponse;
+    use http::StatusCode;
+
+    async fn body_of(response: axum_core::response::Response) -> Vec<u8> {
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_api_error() {
+        let response = ApiResult::ok(vec![1, 2]).into_response();
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(Ok(vec![1, 2]), decode::<Vec<i32>>(&body_of(response).await));
+
+        // 业务错误码默认映射为400，http状态码原样使用，指定的状态码优先
+        let ret: ApiResult<i32> = Err(ApiError::new(1001, "name exists")).into();
+        let response = ret.into_response();
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+        let ret = decode::<i32>(&body_of(response).await).unwrap_err();
+        assert_eq!((1001, Some("name exists")), (ret.code, ret.message.as_deref()));
+        let response = Response::<()>::fail(404, None).into_response();
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+        let response = ApiError::new(1001, "").with_status(StatusCode::CONFLICT).into_response();
+        assert_eq!(StatusCode::CONFLICT, response.status());
+
+        // 成功但没有数据
+        let body = body_of(Response::<()>::success(None).into_response()).await;
+        assert_eq!(Ok(()), decode::<()>(&body));
+        assert_eq!(Ok(None), decode::<Option<i32>>(&body));
+        assert_eq!(DECODE_ERROR_CODE, decode::<i32>(&body).unwrap_err().code);
+        assert_eq!(DECODE_ERROR_CODE, decode::<i32>(b"<html>").unwrap_err().code);
+
+        // 映射按路由配置，只影响配置了映射的路由
+        use axum::routing::get;
+        use tower_service::Service;
+        let routes = || {
+            axum::Router::new()
+                .route("/fail", get(|| async { Response::<()>::fail(4999, None) }))
+                .route("/error", get(|| async { ApiError::new(4999, "") }))
+                .route("/with-status", get(|| async { ApiError::new(4999, "").with_status(StatusCode::CONFLICT) }))
+                .route("/not-found", get(|| async { Response::<()>::fail(404, None) }))
+        };
+        let mut mapped = routes().layer(StatusMappingLayer::new(|code| match code {
+            4999 => StatusCode::SERVICE_UNAVAILABLE,
+            code => default_status_mapping(code),
+        }));
+        let mut plain = routes();
+        let request = |path: &str| http::Request::builder().uri(path).body(axum::body::Body::empty()).unwrap();
+        for (path, status) in [
+            ("/fail", StatusCode::SERVICE_UNAVAILABLE),
+            ("/error", StatusCode::SERVICE_UNAVAILABLE),
+            ("/with-status", StatusCode::CONFLICT),
+            ("/not-found", StatusCode::NOT_FOUND),
+        ] {
+            assert_eq!(status, mapped.call(request(path)).await.unwrap().status());
+        }
+        assert_eq!(StatusCode::BAD_REQUEST, plain.call(request("/fail")).await.unwrap().status());
+    }
+
+    crate::api_errors! {
+        /// 用户相关的错误
+        pub enum UserError {
+            NameExists = (1001, CONFLICT, "name exists"),
+            /// 用户不存在
+            NotFound = (1002, NOT_FOUND, "user not found"),
+            Disabled = (1003, FORBIDDEN, "user disabled"),
+        }
+    }
+
+    crate::api_errors! {
+        enum OrderError {
+            NotFound = (2001, NOT_FOUND, "order not found"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_errors() {
+        assert_eq!(1002, UserError::NotFound.code());
+        assert_eq!(StatusCode::NOT_FOUND, UserError::NotFound.status());
+        assert_eq!("1003: user disabled", UserError::Disabled.to_string());
+
+        let response = UserError::NameExists.into_response();
+        assert_eq!(StatusCode::CONFLICT, response.status());
+        let ret = decode::<i32>(&body_of(response).await).unwrap_err();
+        assert_eq!((1001, Some("name exists")), (ret.code, ret.message.as_deref()));
+
+        let ret: ApiResult<i32> = Err(UserError::Disabled.with_message("banned until tomorrow")).into();
+        let response = ret.into_response();
+        assert_eq!(StatusCode::FORBIDDEN, response.status());
+        let ret = decode::<i32>(&body_of(response).await).unwrap_err();
+        assert_eq!(Some("banned until tomorrow"), ret.message.as_deref());
+
+        // 错误码不是http状态码时，使用定义的状态码而不是映射的400
+        let ret: ApiResult<i32> = Err(ApiError::from(OrderError::NotFound)).into();
+        let response = ret.into_response();
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+        let ret = decode::<i32>(&body_of(response).await).unwrap_err();
+        assert_eq!((2001, Some("order not found")), (ret.code, ret.message.as_deref()));
+
+        // 多个枚举之间的错误码检查
+        let codes = [UserError::CODES, OrderError::CODES].concat();
+        assert_eq!(None, find_duplicate_code(&codes));
+        assert_eq!(Some(1002), find_duplicate_code(&[1001, 1002, 1002]));
+    }
+
+    #[tokio::test]
+    async fn test_field_casing() {
+        use crate::casing::{Cased, FIELD_CASING_HEADER, FieldCasing};
+        use axum_core::extract::FromRequestParts;
+
+        #[derive(serde::Serialize)]
+        struct User {
+            user_name: String,
+            last_login_ms: i64,
+            roles: Vec<Role>,
+            /// map的键属于数据，不转换
+            settings: std::collections::HashMap<String, i32>,
+            extra: serde_json::Value,
+        }
+
+        #[derive(serde::Serialize)]
+        struct Role {
+            role_id: i32,
+        }
+
+        #[derive(serde::Serialize)]
+        struct Status {
+            /// 指定了名称的字段保持不变
+            #[serde(rename = "HTTPStatus")]
+            http_status: u16,
+            #[serde(rename = "userID")]
+            user_id: i32,
+            retry_after_ms: i64,
+            state: State,
+        }
+
+        #[derive(serde::Serialize)]
+        enum State {
+            Locked { locked_until_ms: i64, lock_reason: String },
+        }
+
+        assert_eq!("userName", FieldCasing::Camel.convert_key("user_name"));
+        assert_eq!("userName", FieldCasing::Camel.convert_key("userName"));
+        assert_eq!("last_login_ms", FieldCasing::Snake.convert_key("lastLoginMs"));
+        assert_eq!("last_login_ms", FieldCasing::Snake.convert_key("last_login_ms"));
+        // 连续的大写字母视为一个单词
+        assert_eq!("http_status", FieldCasing::Snake.convert_key("HTTPStatus"));
+        assert_eq!("user_id", FieldCasing::Snake.convert_key("userID"));
+        assert_eq!("parse_xml_file", FieldCasing::Snake.convert_key("parseXMLFile"));
+
+        let casing_of = |header: Option<&str>, extension: Option<FieldCasing>| {
+            let mut request = http::Request::builder();
+            if let Some(header) = header {
+                request = request.header(FIELD_CASING_HEADER, header);
+            }
+            let mut parts = request.body(()).unwrap().into_parts().0;
+            if let Some(extension) = extension {
+                parts.extensions.insert(extension);
+            }
+            async move {
+                FieldCasing::from_request_parts(&mut parts, &())
+                    .await
+                    .unwrap()
+            }
+        };
+        // 请求头优先于路由的设置
+        assert_eq!(FieldCasing::Camel, casing_of(Some("camelCase"), Some(FieldCasing::Snake)).await);
+        assert_eq!(FieldCasing::Camel, casing_of(None, Some(FieldCasing::Camel)).await);
+        assert_eq!(FieldCasing::default(), casing_of(Some("kebab"), None).await);
+
+        let user = || User {
+            user_name: "admin".to_string(),
+            last_login_ms: 1,
+            roles: vec![Role { role_id: 2 }],
+            settings: [("page_size".to_string(), 20)].into(),
+            extra: serde_json::json!({"last_ip": "10.0.0.1"}),
+        };
+        let response = Cased(FieldCasing::Camel, ApiResult::ok(user())).into_response();
+        assert_eq!(Some(FIELD_CASING_HEADER), response.headers().get(http::header::VARY).and_then(|v| v.to_str().ok()));
+        let value: serde_json::Value = serde_json::from_slice(&body_of(response).await).unwrap();
+        assert_eq!(
+            serde_json::json!({"code": 0, "message": null, "data": {
+                "userName": "admin", "lastLoginMs": 1, "roles": [{"roleId": 2}],
+                "settings": {"page_size": 20}, "extra": {"last_ip": "10.0.0.1"}
+            }}),
+            value
+        );
+        let response = FieldCasing::Snake.wrap(Response::success(Some(user()))).into_response();
+        let value: serde_json::Value = serde_json::from_slice(&body_of(response).await).unwrap();
+        assert_eq!(serde_json::json!(2), value["data"]["roles"][0]["role_id"]);
+
+        let status = Status {
+            http_status: 423,
+            user_id: 7,
+            retry_after_ms: 1000,
+            state: State::Locked { locked_until_ms: 2000, lock_reason: "too many attempts".to_string() },
+        };
+        let expected = serde_json::json!({
+            "HTTPStatus": 423, "userID": 7, "retryAfterMs": 1000,
+            "state": {"Locked": {"lockedUntilMs": 2000, "lockReason": "too many attempts"}}
+        });
+        assert_eq!(expected, FieldCasing::Camel.to_value(&status).unwrap());
+        let value = FieldCasing::Snake.to_value(&status).unwrap();
+        assert_eq!(serde_json::json!(423), value["HTTPStatus"]);
+        assert_eq!(serde_json::json!(1000), value["retry_after_ms"]);
+    }
+
+    #[tokio::test]
+    async fn test_problem() {
+        use crate::problem::{ErrorFormat, PROBLEM_JSON, Problem};
+        use axum_core::extract::FromRequestParts;
+
+        let format_of = |accept: &str| {
+            let mut parts = http::Request::builder()
+                .header(http::header::ACCEPT, accept)
+                .body(())
+                .unwrap()
+                .into_parts()
+                .0;
+            async move {
+                ErrorFormat::from_request_parts(&mut parts, &())
+                    .await
+                    .unwrap()
+            }
+        };
+        assert_eq!(ErrorFormat::Problem, format_of("application/problem+json, application/json;q=0.9").await);
+        assert_eq!(ErrorFormat::Envelope, format_of("application/json").await);
+        assert_eq!(ErrorFormat::Envelope, format_of("application/problem+json;q=0").await);
+
+        let response = ErrorFormat::Problem.wrap(ApiResult::<i32>::err(UserError::NotFound.into())).into_response();
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+        assert_eq!(PROBLEM_JSON, response.headers()[http::header::CONTENT_TYPE]);
+        let value: serde_json::Value = serde_json::from_slice(&body_of(response).await).unwrap();
+        assert_eq!(
+            serde_json::json!({"type": "about:blank", "title": "Not Found", "status": 404, "detail": "user not found", "code": 1002}),
+            value
+        );
+        let problem: Problem = serde_json::from_value(value).unwrap();
+        let error = ApiError::from(problem);
+        assert_eq!((1002, Some("user not found")), (error.code, error.message.as_deref()));
+
+        // 成功以及envelope格式不受影响
+        let response = ErrorFormat::Problem.wrap(ApiResult::ok(1)).into_response();
+        assert_eq!(Ok(1), decode::<i32>(&body_of(response).await));
+        let response = ErrorFormat::Envelope.wrap(ApiResult::<i32>::err(UserError::NotFound.into())).into_response();
+        assert_eq!(1002, decode::<i32>(&body_of(response).await).unwrap_err().code);
+
+        let problem = Problem::from(UserError::Disabled)
+            .with_type("https://example.com/problems/user-disabled")
+            .with_instance("/users/1")
+            .with_extension("until", "2026-01-01");
+        let response = problem.clone().into_response();
+        assert_eq!(StatusCode::FORBIDDEN, response.status());
+        let decoded: Problem = serde_json::from_slice(&body_of(response).await).unwrap();
+        assert_eq!(problem, decoded);
+    }
+
+    #[cfg(feature = "http_page")]
+    #[tokio::test]
+    async fn test_page() {
+        use crate::page::{CursorCodec, CursorError, CursorPage, Page, PageQuery};
+        use axum_core::extract::FromRequestParts;
+
+        let query_of = |uri: &str| {
+            let mut parts = http::Request::builder().uri(uri).body(()).unwrap().into_parts().0;
+            async move { PageQuery::<10, 50>::from_request_parts(&mut parts, &()).await }
+        };
+        let query = query_of("/users?name=a").await.unwrap();
+        assert_eq!((0, 10, None), (query.offset, query.limit, query.cursor));
+        let query = query_of("/users?offset=20&limit=50").await.unwrap();
+        assert_eq!((20, 50), (query.offset, query.limit));
+        for uri in ["/users?limit=0", "/users?limit=51", "/users?limit=-1"] {
+            let response = query_of(uri).await.unwrap_err().into_response();
+            assert_eq!(StatusCode::BAD_REQUEST, response.status());
+        }
+
+        let uri: http::Uri = "/users?name=a%20b&offset=10&limit=10".parse().unwrap();
+        let page = Page::new(vec![1, 2], 25, 10, 10);
+        assert_eq!(Some("/users?name=a+b&offset=20&limit=10".to_string()), page.next_link(&uri));
+        assert_eq!(Some("/users?name=a+b&offset=0&limit=10".to_string()), page.prev_link(&uri));
+        assert_eq!(None, Page::new(vec![1], 25, 20, 10).next_link(&uri));
+        assert_eq!(None, Page::new(vec![1], 25, 0, 10).prev_link(&uri));
+        // 偏移接近上限时不会溢出
+        assert!(!Page::new(vec![1], u64::MAX, u64::MAX - 1, 10).has_next());
+        assert_eq!(None, Page::new(vec![1], u64::MAX, u64::MAX - 1, 10).next_link(&uri));
+        // limit为0时不会一直停在同一页
+        let clamped = Page::new(Vec::<i32>::new(), 25, 24, 0);
+        assert_eq!(1, clamped.limit);
+        assert!(!clamped.has_next());
+        let zero = Page { limit: 0, ..Page::new(vec![1], 25, 10, 10) };
+        assert!(!zero.has_next());
+        assert_eq!(None, zero.next_link(&uri));
+        let value = serde_json::to_value(Response::success(Some(page.map(|id| id * 2)))).unwrap();
+        assert_eq!(serde_json::json!([2, 4]), value["data"]["items"]);
+
+        #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+        struct After {
+            id: i64,
+        }
+        let codec = CursorCodec::new("cursor-secret");
+        let cursor = codec.encode(&After { id: 42 }).unwrap();
+        let page = CursorPage::new(vec![41, 42], Some(cursor.clone()), None);
+        let link = page.next_link(&"/users?name=a".parse().unwrap(), 2).unwrap();
+        assert_eq!(format!("/users?name=a&cursor={cursor}&limit=2"), link);
+
+        let query = query_of(&link).await.unwrap();
+        assert_eq!(Some(After { id: 42 }), query.decode_cursor(&codec).unwrap());
+        assert_eq!(None, query_of("/users").await.unwrap().decode_cursor::<After>(&codec).unwrap());
+        // 其他密钥签名或被修改的游标
+        let ret = CursorCodec::new("other").decode::<After>(&cursor);
+        assert_eq!(Err(CursorError::InvalidSignature), ret);
+        let (_, signature) = cursor.split_once('.').unwrap();
+        let forged = format!("{}.{signature}", base64::Engine::encode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, br#"{"id":1}"#));
+        assert_eq!(Err(CursorError::InvalidSignature), codec.decode::<After>(&forged));
+        assert_eq!(Err(CursorError::Malformed), codec.decode::<After>("abc"));
+    }
+
+    #[cfg(feature = "http_request_id")]
+    #[tokio::test]
+    async fn test_request_id() {
+        use crate::casing::FieldCasing;
+        use crate::problem::Problem;
+        use crate::request_id::{REQUEST_ID_HEADER, RequestId, RequestIdLayer};
+        use axum::routing::get;
+        use tower_service::Service;
+
+        let router = |layer: RequestIdLayer| {
+            axum::Router::new()
+                .route("/ok", get(|id: RequestId| async move { ApiResult::ok(id.0) }))
+                .route("/fail", get(|| async { UserError::NotFound }))
+                .route("/problem", get(|| async { Problem::from(UserError::NotFound) }))
+                // 在其他任务中生成的响应
+                .route("/spawn", get(|| async {
+                    tokio::spawn(async { UserError::NotFound.into_response() }).await.unwrap()
+                }))
+                .route("/camel", get(|| async { FieldCasing::Camel.wrap(Response::success(Some(1))) }))
+                .layer(layer)
+        };
+        let call = |mut router: axum::Router, path: &str, request_id: Option<&str>| {
+            let mut request = http::Request::builder().uri(path);
+            if let Some(request_id) = request_id {
+                request = request.header(REQUEST_ID_HEADER, request_id);
+            }
+            let request = request.body(axum::body::Body::empty()).unwrap();
+            async move {
+                let response = router.call(request).await.unwrap();
+                let request_id = response.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+                let body: serde_json::Value = serde_json::from_slice(&body_of(response).await).unwrap();
+                (request_id, body)
+            }
+        };
+
+        // 沿用请求中的id，默认不注入到响应中
+        let (request_id, body) = call(router(RequestIdLayer::default()), "/ok", Some("client-1")).await;
+        assert_eq!("client-1", request_id);
+        assert_eq!(serde_json::json!({"code": 0, "message": null, "data": "client-1"}), body);
+
+        // 不合法时生成新的uuid v7
+        let (request_id, body) = call(router(RequestIdLayer::default()), "/ok", Some("")).await;
+        let uuid = uuid::Uuid::parse_str(&request_id).unwrap();
+        assert_eq!(Some(uuid::Version::SortRand), uuid.get_version());
+        assert_eq!(serde_json::json!(request_id), body["data"]);
+
+        let layer = RequestIdLayer::default().inject_into_envelope(true);
+        let (request_id, body) = call(router(layer.clone()), "/ok", None).await;
+        assert_eq!(serde_json::json!(request_id), body["request_id"]);
+        let (request_id, body) = call(router(layer.clone()), "/fail", Some("client-2")).await;
+        assert_eq!("client-2", request_id);
+        assert_eq!((serde_json::json!(1002), serde_json::json!("client-2")), (body["code"].clone(), body["request_id"].clone()));
+        let (_, body) = call(router(layer.clone()), "/problem", Some("client-3")).await;
+        assert_eq!(serde_json::json!("client-3"), body["request_id"]);
+        let (_, body) = call(router(layer.clone()), "/spawn", Some("client-4")).await;
+        assert_eq!(serde_json::json!("client-4"), body["request_id"]);
+        let (_, body) = call(router(layer), "/camel", Some("client-5")).await;
+        assert_eq!(serde_json::json!({"code": 0, "message": null, "data": 1, "requestId": "client-5"}), body);
+
+        // 中间件之外不注入
+        let body = body_of(Response::<()>::success(None).into_response()).await;
+        assert_eq!(b"{\"code\":0,\"message\":null,\"data\":null}".to_vec(), body);
+    }
+
+    #[cfg(feature = "http_client_ip")]
+    #[test]
+    fn test_client_ip() {
+        use crate::client_ip::{ClientIp, IpCidr, TrustedProxies};
+        use axum::extract::ConnectInfo;
+        use std::net::{IpAddr, SocketAddr};
+
+        let cidr: IpCidr = "10.0.0.0/8".parse().unwrap();
+        assert!(cidr.contains(&"10.1.2.3".parse().unwrap()));
+        assert!(cidr.contains(&"::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!cidr.contains(&"11.0.0.1".parse().unwrap()));
+        assert!("2001:db8::/32".parse::<IpCidr>().unwrap().contains(&"2001:db8:1::1".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<IpCidr>().is_err());
+        assert!("proxy".parse::<IpCidr>().is_err());
+
+        let trusted = TrustedProxies::parse(["10.0.0.0/8", "::1"]).unwrap();
+        let resolve = |peer: &str, headers: &[(&str, &str)], trusted: Option<&TrustedProxies>| {
+            let mut request = http::Request::builder();
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            let mut parts = request.body(()).unwrap().into_parts().0;
+            parts.extensions.insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+            if let Some(trusted) = trusted {
+                parts.extensions.insert(trusted.clone());
+            }
+            ClientIp::from_parts(&parts).map(|ip| ip.0.to_string())
+        };
+        let ip = |ip: &str| Some(ip.parse::<IpAddr>().unwrap().to_string());
+
+        // 未配置可信代理或直连地址不可信时忽略代理头
+        let spoofed = [("x-forwarded-for", "1.1.1.1")];
+        assert_eq!(ip("10.0.0.1"), resolve("10.0.0.1:80", &spoofed, None));
+        assert_eq!(ip("8.8.8.8"), resolve("8.8.8.8:80", &spoofed, Some(&trusted)));
+
+        // 跳过可信代理，伪造的最左侧地址被忽略
+        let xff = [("x-forwarded-for", "6.6.6.6, 1.1.1.1, 10.0.0.2")];
+        assert_eq!(ip("1.1.1.1"), resolve("10.0.0.1:80", &xff, Some(&trusted)));
+        // 全部可信时为最左侧的地址
+        let xff = [("x-forwarded-for", "10.0.0.3, 10.0.0.2")];
+        assert_eq!(ip("10.0.0.3"), resolve("10.0.0.1:80", &xff, Some(&trusted)));
+
+        // Forwarded优先于X-Forwarded-For
+        let forwarded = [
+            ("forwarded", r#"for=192.0.2.60;proto=http, for="[2001:db8:cafe::17]:4711";by=10.0.0.9"#),
+            ("x-forwarded-for", "1.1.1.1"),
+        ];
+        assert_eq!(ip("2001:db8:cafe::17"), resolve("[::1]:443", &forwarded, Some(&trusted)));
+        let forwarded = [("forwarded", "for=192.0.2.60, for=10.0.0.5:8080")];
+        assert_eq!(ip("192.0.2.60"), resolve("10.0.0.1:80", &forwarded, Some(&trusted)));
+        // 混淆的标识之前的内容不可信
+        let forwarded = [("forwarded", "for=192.0.2.60, for=_hidden, for=10.0.0.5")];
+        assert_eq!(ip("10.0.0.5"), resolve("10.0.0.1:80", &forwarded, Some(&trusted)));
+
+        let real_ip = [("x-real-ip", "3.3.3.3")];
+        assert_eq!(ip("3.3.3.3"), resolve("10.0.0.1:80", &real_ip, Some(&trusted)));
+        assert_eq!(ip("9.9.9.9"), resolve("9.9.9.9:80", &real_ip, Some(&trusted)));
+    }
+
+    #[tokio::test]
+    async fn test_token_source() {
+        use crate::token_source::{TokenError, TokenSource, TokenSources};
+        use crate::utils::get_bear_token;
+
+        let parts = |uri: &str, headers: &[(&str, &str)], sources: Option<TokenSources>| {
+            let mut request = http::Request::builder().uri(uri);
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            let mut parts = request.body(()).unwrap().into_parts().0;
+            if let Some(sources) = sources {
+                parts.extensions.insert(sources);
+            }
+            parts
+        };
+
+        // 默认只读取Authorization
+        let mut p = parts("/?access_token=q", &[("authorization", "Bearer abc")], None);
+        assert_eq!(Ok("abc".to_string()), get_bear_token(&mut p).await);
+        let mut p = parts("/?access_token=q", &[], None);
+        assert_eq!(
+            Err(TokenError::Missing(vec![TokenSource::Authorization])),
+            get_bear_token(&mut p).await
+        );
+        let mut p = parts("/", &[("authorization", "Basic YTpi")], None);
+        assert_eq!(
+            Err(TokenError::Malformed(TokenSource::Authorization)),
+            get_bear_token(&mut p).await
+        );
+
+        let sources = TokenSources::new()
+            .header(http::HeaderName::from_static("x-token"))
+            .query("access_token")
+            .cookie("token")
+            .websocket_protocol("bearer.");
+        let extract = |uri: &str, headers: &[(&str, &str)]| {
+            TokenSources::extract_from_parts(&parts(uri, headers, Some(sources.clone())))
+        };
+        // 按顺序查找
+        assert_eq!(Ok("h".to_string()), extract("/?access_token=q", &[("x-token", "h")]));
+        assert_eq!(Ok("a b".to_string()), extract("/?a=1&access_token=a%20b", &[]));
+        assert_eq!(Ok("c".to_string()), extract("/", &[("cookie", "theme=dark; token=c")]));
+        assert_eq!(
+            Ok("w.x.y".to_string()),
+            extract("/", &[("sec-websocket-protocol", "chat, bearer.w.x.y")])
+        );
+        // 空值视为没有
+        assert_eq!(Ok("q".to_string()), extract("/?access_token=q", &[("x-token", "")]));
+
+        let error = extract("/", &[("cookie", "theme=dark")]).unwrap_err();
+        assert_eq!(
+            "missing token, tried: authorization header, header `x-token`, query `access_token`, \
+             cookie `token`, sec-websocket-protocol `bearer.`",
+            error.to_string()
+        );
+        assert_eq!(Some(StatusCode::UNAUTHORIZED), ApiError::from(error).status);
+    }
+
+    #[cfg(feature = "http_credentials")]
+    #[tokio::test]
+    async fn test_credentials() {
+        use crate::credentials::{
+            ApiKeyAuth, ApiKeyHeader, BASIC_CHALLENGE, BasicAuth, constant_time_eq,
+            constant_time_eq_any,
+        };
+        use axum::routing::get;
+        use tower_service::Service;
+
+        assert!(constant_time_eq("secret", "secret"));
+        assert!(!constant_time_eq("secret", "secret2"));
+        assert!(constant_time_eq_any("new", ["old", "new"]));
+        assert!(!constant_time_eq_any("other", ["old", "new"]));
+
+        let mut router = axum::Router::new()
+            .route(
+                "/admin",
+                get(|auth: BasicAuth| async move {
+                    auth.verify("admin", "p@ss").map_err(ApiError::from)?;
+                    Ok::<_, ApiError>(ApiResult::ok(auth.username))
+                }),
+            )
+            .route(
+                "/legacy",
+                get(|auth: ApiKeyAuth| async move {
+                    auth.verify_any(["old-key", "new-key"])?;
+                    Ok::<_, crate::credentials::ApiKeyRejection>(ApiResult::ok(()))
+                }),
+            )
+            .layer(axum::Extension(ApiKeyHeader(http::HeaderName::from_static("x-legacy-key"))));
+        let mut call = |path: &str, header: Option<(&str, &str)>| {
+            let mut request = http::Request::builder().uri(path);
+            if let Some((name, value)) = header {
+                request = request.header(name, value);
+            }
+            router.call(request.body(axum::body::Body::empty()).unwrap())
+        };
+
+        // admin:p@ss
+        let response = call("/admin", Some(("authorization", "Basic YWRtaW46cEBzcw=="))).await.unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(Ok("admin".to_string()), decode::<String>(&body_of(response).await));
+
+        // 缺少凭据时带有质询，响应为统一的结构
+        let response = call("/admin", None).await.unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+        assert_eq!(BASIC_CHALLENGE, response.headers()[http::header::WWW_AUTHENTICATE]);
+        let error = decode::<String>(&body_of(response).await).unwrap_err();
+        assert_eq!(401, error.code);
+        assert_eq!(Some("basic credentials missing".to_string()), error.message);
+
+        // admin:wrong
+        let response = call("/admin", Some(("authorization", "Basic YWRtaW46d3Jvbmc="))).await.unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+        let response = call("/admin", Some(("authorization", "Basic !!!"))).await.unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+        // 按路由配置的请求头，轮换期间新旧key都可用
+        let response = call("/legacy", Some(("x-legacy-key", "old-key"))).await.unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        let response = call("/legacy", Some(("x-api-key", "new-key"))).await.unwrap();
+        let error = decode::<()>(&body_of(response).await).unwrap_err();
+        assert_eq!(Some("api key missing in header `x-legacy-key`".to_string()), error.message);
+        let response = call("/legacy", Some(("x-legacy-key", "bad-key"))).await.unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    }
+
+    #[cfg(feature = "http_client")]
+    #[tokio::test]
+    async fn test_client() {
+        use crate::client::{ApiClient, NETWORK_ERROR_CODE, TIMEOUT_ERROR_CODE};
+        use crate::problem::Problem;
+        use crate::token_source::TokenSources;
+        use axum::routing::{get, post};
+        use std::time::Duration;
+
+        let router = axum::Router::new()
+            .route(
+                "/me",
+                get(|parts: http::request::Parts| async move {
+                    ApiResult::from(TokenSources::extract_from_parts(&parts).map_err(ApiError::from))
+                }),
+            )
+            .route(
+                "/echo",
+                post(|axum::Json(values): axum::Json<Vec<i32>>| async move { ApiResult::ok(values) }),
+            )
+            .route("/empty", get(|| async { ApiResult::ok(()) }))
+            .route("/user", get(|| async { UserError::NotFound }))
+            .route("/problem", get(|| async { Problem::from(UserError::Disabled) }))
+            .route("/gateway", get(|| async { (StatusCode::BAD_GATEWAY, "upstream down") }))
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    ApiResult::ok(())
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let client = ApiClient::new(format!("http://{addr}/"));
+        let error = client.get::<String>("/me").await.unwrap_err();
+        assert_eq!(401, error.code);
+        assert_eq!(Some(StatusCode::UNAUTHORIZED), error.status);
+
+        // 克隆的客户端共享令牌
+        let cloned = client.clone();
+        client.set_token(Some("abc".to_string()));
+        assert_eq!(Ok("abc".to_string()), cloned.get::<String>("me").await);
+
+        assert_eq!(Ok(vec![3, 2, 1]), client.post::<_, Vec<i32>>("/echo", &[3, 2, 1]).await);
+        assert_eq!(Ok(()), client.get::<()>("/empty").await);
+
+        // 业务错误与问题响应都解析为ApiError
+        let error = client.get::<Vec<String>>("/user").await.unwrap_err();
+        assert_eq!((1002, Some(StatusCode::NOT_FOUND)), (error.code, error.status));
+        let error = client.get::<Vec<String>>("/problem").await.unwrap_err();
+        assert_eq!((1003, Some(StatusCode::FORBIDDEN)), (error.code, error.status));
+        let error = client.get::<()>("/gateway").await.unwrap_err();
+        assert_eq!(ApiError::from_status(StatusCode::BAD_GATEWAY, "upstream down"), error);
+        // 成功的响应与类型不匹配
+        let error = client.get::<Vec<i32>>("/me").await.unwrap_err();
+        assert_eq!(DECODE_ERROR_CODE, error.code);
+
+        let error = client
+            .clone()
+            .with_timeout(Duration::from_millis(50))
+            .get::<()>("/slow")
+            .await
+            .unwrap_err();
+        assert_eq!(TIMEOUT_ERROR_CODE, error.code);
+
+        let unreachable = ApiClient::new("http://127.0.0.1:1");
+        assert_eq!(NETWORK_ERROR_CODE, unreachable.get::<()>("/").await.unwrap_err().code);
+    }
+}
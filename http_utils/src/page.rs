@@ -0,0 +1,266 @@
+/// 分页，作为[crate::response::Response]的`data`返回
+/// - [Page]：按偏移分页
+/// - [CursorPage]：按游标分页，游标由[CursorCodec]签名，客户端无法伪造或修改
+///
+/// 处理函数用[PageQuery]提取`offset`/`limit`/`cursor`参数，`limit`的默认值与上限由泛型参数指定，
+/// 超出范围时返回400
+///
+use crate::api_error::ApiError;
+use axum_core::extract::FromRequestParts;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use http::request::Parts;
+use http::{StatusCode, Uri};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt::{Display, Formatter};
+
+const OFFSET: &str = "offset";
+const LIMIT: &str = "limit";
+const CURSOR: &str = "cursor";
+
+/// 按偏移分页的结果
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: u64,
+    pub offset: u64,
+    pub limit: u32,
+}
+
+impl<T> Page<T> {
+    /// [limit]为0时按1处理，与[PageQuery]的取值范围一致，否则下一页的偏移不变，永远有下一页
+    pub fn new(items: Vec<T>, total: u64, offset: u64, limit: u32) -> Self {
+        Page {
+            items,
+            total,
+            offset,
+            limit: limit.max(1),
+        }
+    }
+
+    /// 直接构造的[Page]的`limit`为0时没有下一页
+    pub fn has_next(&self) -> bool {
+        self.limit > 0 && self.next_offset() < self.total
+    }
+
+    /// 下一页的偏移，溢出时为[u64::MAX]
+    fn next_offset(&self) -> u64 {
+        self.offset.saturating_add(self.limit as u64)
+    }
+
+    pub fn has_prev(&self) -> bool {
+        self.offset > 0
+    }
+
+    /// 下一页的链接，保留[uri]中的其他查询参数
+    pub fn next_link(&self, uri: &Uri) -> Option<String> {
+        self.has_next().then(|| {
+            page_link(
+                uri,
+                &[
+                    (OFFSET, self.next_offset().to_string()),
+                    (LIMIT, self.limit.to_string()),
+                ],
+            )
+        })
+    }
+
+    /// 上一页的链接，保留[uri]中的其他查询参数
+    pub fn prev_link(&self, uri: &Uri) -> Option<String> {
+        self.has_prev().then(|| {
+            page_link(
+                uri,
+                &[
+                    (OFFSET, self.offset.saturating_sub(self.limit as u64).to_string()),
+                    (LIMIT, self.limit.to_string()),
+                ],
+            )
+        })
+    }
+
+    pub fn map<R>(self, f: impl FnMut(T) -> R) -> Page<R> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+            offset: self.offset,
+            limit: self.limit,
+        }
+    }
+}
+
+/// 按游标分页的结果，游标为[CursorCodec]生成的不透明字符串
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+}
+
+impl<T> CursorPage<T> {
+    pub fn new(items: Vec<T>, next_cursor: Option<String>, prev_cursor: Option<String>) -> Self {
+        CursorPage {
+            items,
+            next_cursor,
+            prev_cursor,
+        }
+    }
+
+    pub fn next_link(&self, uri: &Uri, limit: u32) -> Option<String> {
+        self.next_cursor
+            .as_ref()
+            .map(|cursor| page_link(uri, &[(CURSOR, cursor.clone()), (LIMIT, limit.to_string())]))
+    }
+
+    pub fn prev_link(&self, uri: &Uri, limit: u32) -> Option<String> {
+        self.prev_cursor
+            .as_ref()
+            .map(|cursor| page_link(uri, &[(CURSOR, cursor.clone()), (LIMIT, limit.to_string())]))
+    }
+}
+
+/// 替换[uri]中的分页参数
+fn page_link(uri: &Uri, params: &[(&str, String)]) -> String {
+    let mut query: Vec<(String, String)> = uri
+        .query()
+        .and_then(|query| serde_urlencoded::from_str(query).ok())
+        .unwrap_or_default();
+    query.retain(|(key, _)| key != OFFSET && key != LIMIT && key != CURSOR);
+    query.extend(params.iter().map(|(key, value)| (key.to_string(), value.clone())));
+    match serde_urlencoded::to_string(&query) {
+        Ok(query) if !query.is_empty() => format!("{}?{}", uri.path(), query),
+        _ => uri.path().to_string(),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CursorError {
+    /// 格式错误
+    Malformed,
+    /// 签名不匹配
+    InvalidSignature,
+    Decode(String),
+}
+
+impl Display for CursorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CursorError::Malformed => write!(f, "malformed cursor"),
+            CursorError::InvalidSignature => write!(f, "invalid cursor signature"),
+            CursorError::Decode(e) => write!(f, "invalid cursor: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for CursorError {}
+
+impl From<CursorError> for ApiError {
+    fn from(value: CursorError) -> Self {
+        ApiError::from_status(StatusCode::BAD_REQUEST, value.to_string())
+    }
+}
+
+/// 游标的编解码，游标内容为json，格式为`base64url(json).base64url(hmac_sha256(json))`
+#[derive(Clone)]
+pub struct CursorCodec {
+    secret: Vec<u8>,
+}
+
+impl CursorCodec {
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        CursorCodec {
+            secret: secret.into(),
+        }
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        // hmac接受任意长度的密钥
+        Hmac::<Sha256>::new_from_slice(&self.secret).expect("hmac accepts keys of any length")
+    }
+
+    pub fn encode<C: Serialize>(&self, cursor: &C) -> Result<String, CursorError> {
+        let json = serde_json::to_vec(cursor).map_err(|e| CursorError::Decode(e.to_string()))?;
+        let mut mac = self.mac();
+        mac.update(&json);
+        let signature = mac.finalize().into_bytes();
+        Ok(format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(&json),
+            URL_SAFE_NO_PAD.encode(signature)
+        ))
+    }
+
+    pub fn decode<C: DeserializeOwned>(&self, cursor: &str) -> Result<C, CursorError> {
+        let (json, signature) = cursor.split_once('.').ok_or(CursorError::Malformed)?;
+        let json = URL_SAFE_NO_PAD
+            .decode(json)
+            .map_err(|_| CursorError::Malformed)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| CursorError::Malformed)?;
+        let mut mac = self.mac();
+        mac.update(&json);
+        mac.verify_slice(&signature)
+            .map_err(|_| CursorError::InvalidSignature)?;
+        serde_json::from_slice(&json).map_err(|e| CursorError::Decode(e.to_string()))
+    }
+}
+
+#[derive(Deserialize)]
+struct RawPageQuery {
+    offset: Option<u64>,
+    limit: Option<u32>,
+    cursor: Option<String>,
+}
+
+/// 分页参数，`limit`未指定时为`DEFAULT_LIMIT`，范围为`1..=MAX_LIMIT`
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageQuery<const DEFAULT_LIMIT: u32 = 20, const MAX_LIMIT: u32 = 100> {
+    pub offset: u64,
+    pub limit: u32,
+    pub cursor: Option<String>,
+}
+
+impl<const DEFAULT_LIMIT: u32, const MAX_LIMIT: u32> PageQuery<DEFAULT_LIMIT, MAX_LIMIT> {
+    pub fn from_query(query: Option<&str>) -> Result<Self, ApiError> {
+        let raw: RawPageQuery = serde_urlencoded::from_str(query.unwrap_or_default())
+            .map_err(|e| ApiError::from_status(StatusCode::BAD_REQUEST, e.to_string()))?;
+        let limit = raw.limit.unwrap_or(DEFAULT_LIMIT);
+        if limit == 0 || limit > MAX_LIMIT {
+            return Err(ApiError::from_status(
+                StatusCode::BAD_REQUEST,
+                format!("limit must be between 1 and {MAX_LIMIT}"),
+            ));
+        }
+        Ok(PageQuery {
+            offset: raw.offset.unwrap_or_default(),
+            limit,
+            cursor: raw.cursor.filter(|cursor| !cursor.is_empty()),
+        })
+    }
+
+    /// 校验并解析游标，没有游标时返回`None`
+    pub fn decode_cursor<C: DeserializeOwned>(
+        &self,
+        codec: &CursorCodec,
+    ) -> Result<Option<C>, CursorError> {
+        self.cursor
+            .as_deref()
+            .map(|cursor| codec.decode(cursor))
+            .transpose()
+    }
+}
+
+impl<State, const DEFAULT_LIMIT: u32, const MAX_LIMIT: u32> FromRequestParts<State>
+    for PageQuery<DEFAULT_LIMIT, MAX_LIMIT>
+where
+    State: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &State) -> Result<Self, Self::Rejection> {
+        PageQuery::from_query(parts.uri.query())
+    }
+}
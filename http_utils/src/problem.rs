@@ -0,0 +1,207 @@
+/// RFC 7807 `application/problem+json`格式的错误响应
+/// 与[crate::response::Response]使用同一套错误定义([ApiError]与[crate::api_errors]定义的枚举)，
+/// 错误码放在扩展字段`code`中
+///
+/// 处理函数通过[ErrorFormat]提取器按`Accept`头选择错误格式，再用[Formatted]包装响应，
+/// 如`format.wrap(ApiResult::ok(user))`，成功时始终使用`code/message/data`结构
+///
+use crate::api_error::{ApiError, ApiResult, REQUEST_ID_FIELD, json_response};
+use crate::response::Response;
+use axum_core::extract::FromRequestParts;
+use axum_core::response::IntoResponse;
+use http::StatusCode;
+use http::request::Parts;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::convert::Infallible;
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// 默认的问题类型
+pub const ABOUT_BLANK: &str = "about:blank";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Problem {
+    #[serde(rename = "type", default = "about_blank")]
+    pub problem_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    /// 扩展字段
+    #[serde(flatten)]
+    pub extensions: Map<String, Value>,
+}
+
+fn about_blank() -> String {
+    ABOUT_BLANK.to_string()
+}
+
+impl Problem {
+    /// 类型为`about:blank`，标题为状态码的标准描述
+    pub fn new(status: StatusCode) -> Self {
+        Problem {
+            problem_type: about_blank(),
+            title: status.canonical_reason().map(str::to_string),
+            status: Some(status.as_u16()),
+            detail: None,
+            instance: None,
+            extensions: Map::new(),
+        }
+    }
+
+    pub fn with_type(mut self, problem_type: impl Into<String>) -> Self {
+        self.problem_type = problem_type.into();
+        self
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn with_instance(mut self, instance: impl Into<String>) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    /// 添加扩展字段，序列化失败时忽略
+    pub fn with_extension(mut self, key: impl Into<String>, value: impl Serialize) -> Self {
+        if let Ok(value) = serde_json::to_value(value) {
+            self.extensions.insert(key.into(), value);
+        }
+        self
+    }
+
+    /// 扩展字段中的错误码
+    pub fn code(&self) -> Option<i32> {
+        self.extensions
+            .get("code")
+            .and_then(Value::as_i64)
+            .and_then(|code| i32::try_from(code).ok())
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        self.status
+            .and_then(|status| StatusCode::from_u16(status).ok())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+impl From<ApiError> for Problem {
+    fn from(value: ApiError) -> Self {
+        let problem = Problem::new(value.status()).with_extension("code", value.code);
+        match value.message {
+            Some(message) => problem.with_detail(message),
+            None => problem,
+        }
+    }
+}
+
+/// 客户端将问题转换为[ApiError]，没有错误码时使用状态码
+impl From<Problem> for ApiError {
+    fn from(value: Problem) -> Self {
+        let status = value.status_code();
+        ApiError {
+            code: value.code().unwrap_or(status.as_u16() as i32),
+            message: value.detail.or(value.title),
+            status: Some(status),
+        }
+    }
+}
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> axum_core::response::Response {
+        let mut response = json_response(self.status_code(), &self, REQUEST_ID_FIELD);
+        response.headers_mut().insert(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static(PROBLEM_JSON),
+        );
+        response
+    }
+}
+
+/// 错误响应的格式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// `code/message/data`结构
+    #[default]
+    Envelope,
+    Problem,
+}
+
+impl ErrorFormat {
+    /// `Accept`中接受`application/problem+json`时使用[ErrorFormat::Problem]
+    pub fn from_accept(accept: &str) -> Self {
+        let accepts_problem = accept.split(',').any(|media_range| {
+            let mut params = media_range.split(';');
+            let media_type = params.next().unwrap_or_default().trim();
+            let rejected = params.any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("q=")
+                    .and_then(|q| q.trim().parse::<f32>().ok())
+                    .is_some_and(|q| q <= 0.0)
+            });
+            media_type.eq_ignore_ascii_case(PROBLEM_JSON) && !rejected
+        });
+        if accepts_problem {
+            ErrorFormat::Problem
+        } else {
+            ErrorFormat::Envelope
+        }
+    }
+
+    pub fn wrap<T>(self, body: T) -> Formatted<T> {
+        Formatted(self, body)
+    }
+
+    /// 按格式生成错误响应
+    pub fn error_response(&self, error: ApiError) -> axum_core::response::Response {
+        match self {
+            ErrorFormat::Envelope => error.into_response(),
+            ErrorFormat::Problem => Problem::from(error).into_response(),
+        }
+    }
+}
+
+impl<State: Send + Sync> FromRequestParts<State> for ErrorFormat {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &State) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .headers
+            .get(http::header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .map(ErrorFormat::from_accept)
+            .unwrap_or_default())
+    }
+}
+
+/// 按[ErrorFormat]生成错误响应
+pub struct Formatted<T>(pub ErrorFormat, pub T);
+
+impl IntoResponse for Formatted<ApiError> {
+    fn into_response(self) -> axum_core::response::Response {
+        self.0.error_response(self.1)
+    }
+}
+
+impl<T: Serialize> IntoResponse for Formatted<ApiResult<T>> {
+    fn into_response(self) -> axum_core::response::Response {
+        let Formatted(format, ApiResult(result)) = self;
+        match result {
+            Ok(data) => Response::success(Some(data)).into_response(),
+            Err(e) => format.error_response(e),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+/// 请求id中间件
+/// - 请求带有合法的[REQUEST_ID_HEADER]时沿用，否则用[uuid_utils::UuidV7Generator]生成
+/// - 处理请求时进入包含`request_id`的`tracing` span
+/// - 响应头中返回请求id
+/// - 开启[RequestIdLayer::inject_into_envelope]后，由[crate::response::Response]，[crate::api_error::ApiError]，
+///   [crate::problem::Problem]生成的响应体会添加`request_id`字段(camelCase时为`requestId`)，
+///   响应在扩展中带有序列化后的响应体，中间件在返回时添加字段，处理函数在其他任务中生成的响应同样有效；
+///   中间件与处理函数之间不要有改写响应体的层
+///
+/// 处理函数可以用[RequestId]提取器获取当前请求的id
+///
+use axum_core::extract::FromRequestParts;
+use http::request::Parts;
+use http::{HeaderName, HeaderValue, Request};
+use serde::de::IgnoredAny;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tracing::Instrument;
+use tracing::instrument::Instrumented;
+use uuid_utils::{SharedUuidV7Generator, UuidV7Generator};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// 接受的请求id的最大长度
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// 可以添加请求id的json对象响应体，放在响应扩展中
+#[derive(Clone)]
+struct InjectableBody {
+    body: bytes::Bytes,
+    field: &'static str,
+}
+
+impl InjectableBody {
+    /// 在对象的末尾添加请求id字段，不是json对象或已经有同名字段时返回`None`
+    fn inject(&self, request_id: &str) -> Option<Vec<u8>> {
+        let fields = serde_json::from_slice::<HashMap<String, IgnoredAny>>(&self.body).ok()?;
+        if fields.contains_key(self.field) {
+            return None;
+        }
+        let head = self.body.trim_ascii_end().strip_suffix(b"}")?;
+        let mut body = Vec::with_capacity(self.body.len() + self.field.len() + request_id.len() + 8);
+        body.extend_from_slice(head);
+        if !fields.is_empty() {
+            body.push(b',');
+        }
+        serde_json::to_writer(&mut body, self.field).ok()?;
+        body.push(b':');
+        serde_json::to_writer(&mut body, request_id).ok()?;
+        body.push(b'}');
+        Some(body)
+    }
+}
+
+/// 标记[response]的响应体[body]可以添加名为[field]的请求id字段
+pub(crate) fn injectable(
+    mut response: axum_core::response::Response,
+    body: bytes::Bytes,
+    field: &'static str,
+) -> axum_core::response::Response {
+    response
+        .extensions_mut()
+        .insert(InjectableBody { body, field });
+    response
+}
+
+/// 当前请求的id
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<State: Send + Sync> FromRequestParts<State> for RequestId {
+    type Rejection = Infallible;
+
+    /// 没有经过[RequestIdLayer]时生成新的id
+    async fn from_request_parts(parts: &mut Parts, _state: &State) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<RequestId>()
+            .cloned()
+            .unwrap_or_else(|| RequestId(uuid::Uuid::now_v7().to_string())))
+    }
+}
+
+fn is_valid_request_id(value: &HeaderValue) -> bool {
+    let bytes = value.as_bytes();
+    !bytes.is_empty()
+        && bytes.len() <= MAX_REQUEST_ID_LEN
+        && bytes.iter().all(|b| b.is_ascii_graphic())
+}
+
+#[derive(Clone)]
+pub struct RequestIdLayer {
+    header: HeaderName,
+    generator: SharedUuidV7Generator,
+    inject: bool,
+}
+
+impl Default for RequestIdLayer {
+    fn default() -> Self {
+        RequestIdLayer::new(UuidV7Generator::with_default_counter_bits(0))
+    }
+}
+
+impl RequestIdLayer {
+    /// [generator]的节点id用于区分不同的实例
+    pub fn new(generator: UuidV7Generator) -> Self {
+        RequestIdLayer {
+            header: HeaderName::from_static(REQUEST_ID_HEADER),
+            generator: SharedUuidV7Generator::from_generator(generator),
+            inject: false,
+        }
+    }
+
+    /// 使用其他的请求头，如`x-correlation-id`
+    pub fn with_header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// 将请求id注入到[crate::response::Response]与[crate::problem::Problem]的响应体中
+    pub fn inject_into_envelope(mut self, inject: bool) -> Self {
+        self.inject = inject;
+        self
+    }
+
+    fn generate(&self) -> String {
+        self.generator
+            .now_uuid()
+            .unwrap_or_else(|e| {
+                // 每个请求都需要id，时钟异常时记录下来，改用不带节点id的uuid
+                tracing::warn!(error = %e, "request id generator failed, using a plain uuid v7");
+                uuid::Uuid::now_v7()
+            })
+            .to_string()
+    }
+}
+
+impl<S> tower_layer::Layer<S> for RequestIdLayer {
+    type Service = RequestIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestIdService<S> {
+    inner: S,
+    layer: RequestIdLayer,
+}
+
+impl<S, ReqBody, ResBody> tower_service::Service<Request<ReqBody>> for RequestIdService<S>
+where
+    S: tower_service::Service<Request<ReqBody>, Response = http::Response<ResBody>>,
+    ResBody: From<Vec<u8>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = RequestIdFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        let header_value = request
+            .headers()
+            .get(&self.layer.header)
+            .filter(|value| is_valid_request_id(value))
+            .cloned()
+            .or_else(|| HeaderValue::from_str(&self.layer.generate()).ok());
+        // 合法的请求id与生成的uuid都是可见的ascii字符
+        let header_value = header_value.unwrap_or(HeaderValue::from_static("unknown"));
+        let request_id = header_value.to_str().unwrap_or_default().to_string();
+        request
+            .headers_mut()
+            .insert(self.layer.header.clone(), header_value.clone());
+        request
+            .extensions_mut()
+            .insert(RequestId(request_id.clone()));
+        let span = tracing::info_span!(
+            "request",
+            request_id = %request_id,
+            method = %request.method(),
+            path = %request.uri().path(),
+        );
+        RequestIdFuture {
+            inner: self.inner.call(request).instrument(span),
+            header: self.layer.header.clone(),
+            header_value,
+            injected: self.layer.inject.then_some(request_id),
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct RequestIdFuture<F> {
+    #[pin]
+    inner: Instrumented<F>,
+    header: HeaderName,
+    header_value: HeaderValue,
+    injected: Option<String>,
+}
+
+impl<F, ResBody, E> Future for RequestIdFuture<F>
+where
+    F: Future<Output = Result<http::Response<ResBody>, E>>,
+    ResBody: From<Vec<u8>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match this.inner.poll(cx) {
+            Poll::Ready(Ok(mut response)) => {
+                let injectable = response.extensions_mut().remove::<InjectableBody>();
+                if let Some(request_id) = this.injected.as_deref()
+                    && let Some(body) = injectable.and_then(|body| body.inject(request_id))
+                {
+                    response.headers_mut().remove(http::header::CONTENT_LENGTH);
+                    *response.body_mut() = ResBody::from(body);
+                }
+                response
+                    .headers_mut()
+                    .insert(this.header.clone(), this.header_value.clone());
+                Poll::Ready(Ok(response))
+            }
+            ret => ret,
+        }
+    }
+}
@@ -1,23 +1,14 @@
 #[cfg(feature = "http_types")]
 use serde::{Deserialize, Serialize};
 
-#[cfg(all(feature = "http_types", feature = "http_serde_camel_case"))]
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct Response<T, CodeType = i32, >
-{
-    pub code: CodeType,
-    pub message: Option<String>,
-    pub data: Option<T>
-}
-
-#[cfg(all(feature = "http_types", not(feature = "http_serde_camel_case")))]
+/// 统一的响应结构，字段命名风格在序列化时选择，见[crate::casing]
+#[cfg(feature = "http_types")]
 #[derive(Serialize, Deserialize)]
 pub struct Response<T, CodeType = i32, >
 {
     pub code: CodeType,
     pub message: Option<String>,
-    pub data: Option<T>
+    pub data: Option<T>,
 }
 
 #[cfg(feature = "http_types")]
@@ -35,7 +26,7 @@ impl<T> Response<T>
         Response {
             code,
             message,
-            data: None
+            data: None,
         }
     }
 
@@ -43,7 +34,7 @@ impl<T> Response<T>
         Response {
             code,
             message: msg,
-            data: Some(data)
+            data: Some(data),
         }
     }
 
@@ -53,7 +44,7 @@ impl<T> Response<T>
 
     /// 是否成功
     pub fn is_success(&self) -> bool {
-        return self.code == 0;
+        self.code == 0
     }
 
     // pub fn data(&self) -> Option<&T> {
@@ -0,0 +1,187 @@
+/// 从请求中获取令牌，按配置的来源依次查找，返回第一个找到的令牌
+/// - [TokenSource::Authorization]：`Authorization: Bearer <token>`
+/// - [TokenSource::Header]：自定义请求头，值为令牌
+/// - [TokenSource::Query]：查询参数，用于下载链接等无法设置请求头的场景
+/// - [TokenSource::Cookie]：cookie
+/// - [TokenSource::WebSocketProtocol]：`Sec-WebSocket-Protocol`中带有指定前缀的协议，
+///   浏览器建立WebSocket连接时无法设置请求头，如`new WebSocket(url, ["bearer." + token])`，
+///   处理函数需要在握手响应中返回选中的协议
+///
+/// 来源通过请求扩展按路由配置，如`router.layer(Extension(TokenSources::new().query("access_token")))`，
+/// 未配置时只使用[TokenSource::Authorization]
+///
+use axum_extra::headers::authorization::Bearer;
+use axum_extra::headers::{Authorization, HeaderMapExt};
+use http::HeaderName;
+use http::request::Parts;
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TokenSource {
+    Authorization,
+    Header(HeaderName),
+    Query(String),
+    Cookie(String),
+    /// 协议的前缀
+    WebSocketProtocol(String),
+}
+
+impl Display for TokenSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenSource::Authorization => write!(f, "authorization header"),
+            TokenSource::Header(name) => write!(f, "header `{name}`"),
+            TokenSource::Query(name) => write!(f, "query `{name}`"),
+            TokenSource::Cookie(name) => write!(f, "cookie `{name}`"),
+            TokenSource::WebSocketProtocol(prefix) => {
+                write!(f, "sec-websocket-protocol `{prefix}`")
+            }
+        }
+    }
+}
+
+impl TokenSource {
+    /// 获取令牌，没有时返回`Ok(None)`，有值但格式错误时返回错误
+    pub fn extract(&self, parts: &Parts) -> Result<Option<String>, TokenError> {
+        let token = match self {
+            TokenSource::Authorization => parts
+                .headers
+                .typed_try_get::<Authorization<Bearer>>()
+                .map_err(|_| TokenError::Malformed(self.clone()))?
+                .map(|Authorization(bearer)| bearer.token().to_string()),
+            TokenSource::Header(name) => match parts.headers.get(name) {
+                Some(value) => Some(
+                    value
+                        .to_str()
+                        .map_err(|_| TokenError::Malformed(self.clone()))?
+                        .trim()
+                        .to_string(),
+                ),
+                None => None,
+            },
+            TokenSource::Query(name) => parts.uri.query().and_then(|query| {
+                form_urlencoded::parse(query.as_bytes())
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.into_owned())
+            }),
+            TokenSource::Cookie(name) => parts
+                .headers
+                .get_all(http::header::COOKIE)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(';'))
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| key.trim() == name)
+                .map(|(_, value)| value.trim().trim_matches('"').to_string()),
+            TokenSource::WebSocketProtocol(prefix) => parts
+                .headers
+                .get_all(http::header::SEC_WEBSOCKET_PROTOCOL)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(','))
+                .find_map(|protocol| protocol.trim().strip_prefix(prefix.as_str()))
+                .map(str::to_string),
+        };
+        Ok(token.filter(|token| !token.is_empty()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TokenError {
+    /// 所有来源都没有令牌，包含查找过的来源
+    Missing(Vec<TokenSource>),
+    /// 来源中有值但格式错误，如`Authorization`不是Bearer
+    Malformed(TokenSource),
+}
+
+impl Display for TokenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenError::Missing(tried) => {
+                write!(f, "missing token, tried: ")?;
+                for (i, source) in tried.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{source}")?;
+                }
+                Ok(())
+            }
+            TokenError::Malformed(source) => write!(f, "malformed token in {source}"),
+        }
+    }
+}
+
+impl std::error::Error for TokenError {}
+
+#[cfg(feature = "http_types")]
+impl From<TokenError> for crate::api_error::ApiError {
+    fn from(value: TokenError) -> Self {
+        crate::api_error::ApiError::from_status(http::StatusCode::UNAUTHORIZED, value.to_string())
+    }
+}
+
+/// 令牌的来源，按顺序查找
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenSources(Vec<TokenSource>);
+
+impl Default for TokenSources {
+    fn default() -> Self {
+        TokenSources(vec![TokenSource::Authorization])
+    }
+}
+
+impl TokenSources {
+    /// 以[TokenSource::Authorization]开始
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 没有任何来源，需要再添加
+    pub fn empty() -> Self {
+        TokenSources(Vec::new())
+    }
+
+    pub fn with(mut self, source: TokenSource) -> Self {
+        self.0.push(source);
+        self
+    }
+
+    pub fn header(self, name: HeaderName) -> Self {
+        self.with(TokenSource::Header(name))
+    }
+
+    pub fn query(self, name: impl Into<String>) -> Self {
+        self.with(TokenSource::Query(name.into()))
+    }
+
+    pub fn cookie(self, name: impl Into<String>) -> Self {
+        self.with(TokenSource::Cookie(name.into()))
+    }
+
+    pub fn websocket_protocol(self, prefix: impl Into<String>) -> Self {
+        self.with(TokenSource::WebSocketProtocol(prefix.into()))
+    }
+
+    pub fn sources(&self) -> &[TokenSource] {
+        &self.0
+    }
+
+    /// 依次查找，格式错误时立即返回错误
+    pub fn extract(&self, parts: &Parts) -> Result<String, TokenError> {
+        for source in &self.0 {
+            if let Some(token) = source.extract(parts)? {
+                return Ok(token);
+            }
+        }
+        Err(TokenError::Missing(self.0.clone()))
+    }
+
+    /// 使用请求扩展中的[TokenSources]查找，未配置时使用[TokenSources::default]
+    pub fn extract_from_parts(parts: &Parts) -> Result<String, TokenError> {
+        match parts.extensions.get::<TokenSources>() {
+            Some(sources) => sources.extract(parts),
+            None => TokenSources::default().extract(parts),
+        }
+    }
+}
@@ -1,22 +1,7 @@
-use axum_core::RequestPartsExt;
-use axum_core::extract::FromRequestParts;
-use axum_extra::TypedHeader;
-use axum_extra::headers::Authorization;
-use axum_extra::headers::authorization::Bearer;
+use crate::token_source::{TokenError, TokenSources};
 use http::request::Parts;
 
-pub async fn get_bear_token(
-    parts: &mut Parts,
-) -> Result<String, <TypedHeader<Authorization<Bearer>> as FromRequestParts<TypedHeader<Authorization<Bearer>>>>::Rejection>{
-    // Extract the token from the authorization header
-    match parts.extract::<TypedHeader<Authorization<Bearer>>>().await {
-        Ok(TypedHeader(Authorization(bearer))) => {
-            // Decode the user data
-            let bearer_data = bearer.token();
-            Ok(bearer_data.to_owned())
-        }
-        Err(e) => Err(e),
-    }
-    // let TypedHeader(Authorization(bearer)) =
-    //     parts.extract::<TypedHeader<Authorization<Bearer>>>().await;
+/// 获取请求中的令牌，来源由请求扩展中的[TokenSources]配置，默认只读取`Authorization: Bearer`
+pub async fn get_bear_token(parts: &mut Parts) -> Result<String, TokenError> {
+    TokenSources::extract_from_parts(parts)
 }
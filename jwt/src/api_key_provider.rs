@@ -0,0 +1,223 @@
+/// 用于服务间调用的API key授权
+/// key的格式为`{prefix}_{key_id}_{secret}`，前缀可见，便于识别key的用途
+/// 存储中只保存secret的哈希值，见[super::api_key_storage_provider::ApiKeyStorageProvider]
+/// 校验成功后返回与jwt相同的[JwtPayload]，token_id为key_id，因此处理函数可以同时接受两种授权方式，
+/// 见[super::jwt_principal::Principal]
+///
+use crate::api_key_storage_provider::{ApiKeyRecord, ApiKeyStorageProvider};
+use crate::jwt_clock::{JwtClock, SystemClock};
+use crate::jwt_payload::JwtPayload;
+use axum_core::response::{IntoResponse, Response};
+use http::StatusCode;
+use http::request::Parts;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+
+/// 默认读取API key的请求头
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// 新生成的API key
+/// [ApiKey::key]为明文，只在生成时返回一次，需要交给调用方妥善保存
+#[derive(Clone, Debug)]
+pub struct ApiKey {
+    pub key: String,
+    pub key_id: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub expire_ms: Option<i64>,
+}
+
+#[derive(Debug)]
+pub enum ApiKeyError<StorageError> {
+    StorageError(StorageError),
+    /// 请求中没有API key
+    Missing,
+    /// 格式不正确
+    Malformed,
+    NotFound,
+    NotMatch,
+    OutOfDate,
+    /// 缺少指定的权限范围
+    MissingScope(String),
+}
+
+impl<StorageError: std::fmt::Debug> IntoResponse for ApiKeyError<StorageError> {
+    fn into_response(self) -> Response {
+        let (status, error_message) = match self {
+            ApiKeyError::StorageError(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:?}")),
+            ApiKeyError::Missing => (StatusCode::UNAUTHORIZED, "api key missing".to_string()),
+            ApiKeyError::Malformed | ApiKeyError::NotFound | ApiKeyError::NotMatch => {
+                (StatusCode::UNAUTHORIZED, "invalid api key".to_string())
+            }
+            ApiKeyError::OutOfDate => (StatusCode::UNAUTHORIZED, "api key expired".to_string()),
+            ApiKeyError::MissingScope(scope) => {
+                (StatusCode::FORBIDDEN, format!("missing scope: {scope}"))
+            }
+        };
+        let body = axum::Json(serde_json::json!({
+            "error": error_message,
+        }));
+        (status, body).into_response()
+    }
+}
+
+pub struct ApiKeyProvider<ApiKeyStorageProviderType> {
+    prefix: String,
+    header: String,
+    storage_provider: ApiKeyStorageProviderType,
+    clock: Arc<dyn JwtClock>,
+}
+
+impl<ApiKeyStorageProviderType> ApiKeyProvider<ApiKeyStorageProviderType> {
+    /// [prefix]生成的key的可见前缀，如`sk_live`
+    pub fn new(prefix: impl Into<String>, storage_provider: ApiKeyStorageProviderType) -> Self {
+        ApiKeyProvider {
+            prefix: prefix.into(),
+            header: API_KEY_HEADER.to_string(),
+            storage_provider,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 设置时钟，用于计算key的创建与过期时间，默认使用[SystemClock]，一般与[JwtProvider](crate::jwt_provider::JwtProvider)使用同一个时钟
+    pub fn with_clock(mut self, clock: impl JwtClock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// 设置读取API key的请求头，默认为[API_KEY_HEADER]
+    pub fn with_header(mut self, header: impl Into<String>) -> Self {
+        self.header = header.into();
+        self
+    }
+
+    /// 读取API key的请求头
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+
+    /// 生成新的API key并保存
+    /// [expire_in_ms]为[None]时永不过期
+    pub async fn generate<PayloadType>(
+        &self,
+        payload: PayloadType,
+        scopes: Vec<String>,
+        expire_in_ms: Option<i64>,
+    ) -> Result<ApiKey, ApiKeyError<ApiKeyStorageProviderType::Error>>
+    where
+        ApiKeyStorageProviderType: ApiKeyStorageProvider<PayloadType>,
+    {
+        let key_id = scru128::new_string();
+        let mut secret = [0u8; 32];
+        rand::rng().fill_bytes(&mut secret);
+        let secret = to_hex(&secret);
+        let now = self.clock.now_millis();
+        let expire_ms = expire_in_ms.map(|expire_in_ms| now + expire_in_ms);
+        let record = ApiKeyRecord {
+            key_id: key_id.clone(),
+            prefix: self.prefix.clone(),
+            secret_hash: hash_secret(&secret),
+            scopes: scopes.clone(),
+            payload,
+            created_ms: now,
+            expire_ms,
+        };
+        self.storage_provider
+            .save(record)
+            .await
+            .map_err(ApiKeyError::StorageError)?;
+        Ok(ApiKey {
+            key: format!("{}_{key_id}_{secret}", self.prefix),
+            key_id,
+            prefix: self.prefix.clone(),
+            scopes,
+            expire_ms,
+        })
+    }
+
+    /// 校验API key，并检查其是否拥有所有[required_scopes]
+    pub async fn verify<PayloadType>(
+        &self,
+        key: &str,
+        required_scopes: &[&str],
+    ) -> Result<ApiKeyRecord<PayloadType>, ApiKeyError<ApiKeyStorageProviderType::Error>>
+    where
+        ApiKeyStorageProviderType: ApiKeyStorageProvider<PayloadType>,
+    {
+        // key_id与secret中不含'_'，因此从右边拆分，前缀中可以含有'_'
+        let mut parts = key.trim().rsplitn(3, '_');
+        let (Some(secret), Some(key_id), Some(prefix)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(ApiKeyError::Malformed);
+        };
+        let record = match self.storage_provider.load(key_id).await {
+            Ok(Some(record)) => record,
+            Ok(None) => return Err(ApiKeyError::NotFound),
+            Err(e) => return Err(ApiKeyError::StorageError(e)),
+        };
+        let hash = hash_secret(secret);
+        if record.prefix != prefix
+            || !bool::from(hash.as_bytes().ct_eq(record.secret_hash.as_bytes()))
+        {
+            return Err(ApiKeyError::NotMatch);
+        }
+        if let Some(expire_ms) = record.expire_ms
+            && self.clock.now_millis() >= expire_ms
+        {
+            return Err(ApiKeyError::OutOfDate);
+        }
+        if let Some(scope) = required_scopes.iter().find(|s| !record.has_scope(s)) {
+            return Err(ApiKeyError::MissingScope(scope.to_string()));
+        }
+        Ok(record)
+    }
+
+    /// 从[http::request::Parts]的请求头中提取API key并校验
+    /// 成功时返回与jwt相同的[JwtPayload]，永不过期的key的expire_ms为[i64::MAX]
+    pub async fn verify_parts<PayloadType>(
+        &self,
+        parts: &Parts,
+        required_scopes: &[&str],
+    ) -> Result<JwtPayload<PayloadType>, ApiKeyError<ApiKeyStorageProviderType::Error>>
+    where
+        ApiKeyStorageProviderType: ApiKeyStorageProvider<PayloadType>,
+    {
+        let key = parts
+            .headers
+            .get(self.header.as_str())
+            .and_then(|v| v.to_str().ok())
+            .ok_or(ApiKeyError::Missing)?;
+        let record = self.verify::<PayloadType>(key, required_scopes).await?;
+        Ok(JwtPayload {
+            token_id: record.key_id,
+            payload: record.payload,
+            expire_ms: record.expire_ms.unwrap_or(i64::MAX),
+            iss: None,
+            auth_time: Some(record.created_ms / 1000),
+            acr: None,
+            amr: Vec::new(),
+            act: None,
+        })
+    }
+
+    /// 吊销API key
+    pub async fn revoke<PayloadType>(
+        &self,
+        key_id: &str,
+    ) -> Result<Option<ApiKeyRecord<PayloadType>>, ApiKeyStorageProviderType::Error>
+    where
+        ApiKeyStorageProviderType: ApiKeyStorageProvider<PayloadType>,
+    {
+        self.storage_provider.remove(key_id).await
+    }
+}
+
+fn hash_secret(secret: &str) -> String {
+    to_hex(&Sha256::digest(secret.as_bytes()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+/// 保存的API key信息
+/// 不保存明文，只保存密钥部分的哈希值
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ApiKeyRecord<PayloadType> {
+    pub key_id: String,
+    /// 可见的前缀，如`sk_live`
+    pub prefix: String,
+    /// 密钥部分的sha256哈希值(十六进制)
+    pub secret_hash: String,
+    pub scopes: Vec<String>,
+    pub payload: PayloadType,
+    pub created_ms: i64,
+    /// 过期时间，[None]表示永不过期
+    pub expire_ms: Option<i64>,
+}
+
+impl<PayloadType> ApiKeyRecord<PayloadType> {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+pub trait ApiKeyStorageProvider<PayloadType> {
+    type Error;
+
+    /// 保存API key信息
+    fn save(
+        &self,
+        record: ApiKeyRecord<PayloadType>,
+    ) -> impl Future<Output = Result<(), Self::Error>>;
+
+    /// 加载API key信息
+    fn load(
+        &self,
+        key_id: &str,
+    ) -> impl Future<Output = Result<Option<ApiKeyRecord<PayloadType>>, Self::Error>>;
+
+    /// 删除(吊销)API key
+    fn remove(
+        &self,
+        key_id: &str,
+    ) -> impl Future<Output = Result<Option<ApiKeyRecord<PayloadType>>, Self::Error>>;
+}
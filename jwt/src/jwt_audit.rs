@@ -0,0 +1,144 @@
+/// 授权审计
+/// [JwtProvider](crate::jwt_provider::JwtProvider)在授权，校验，删除授权时会通知[JwtAuditObserver]
+/// 默认使用[TracingAuditObserver]，以结构化的`tracing`事件输出审计日志
+///
+use crate::jwt_payload::Actor;
+use crate::jwt_provider::AuthErrorKind;
+use http::request::Parts;
+use http_utils::client_ip::ClientIp;
+
+/// 发起请求的客户端信息
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClientInfo {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn new(ip: Option<String>, user_agent: Option<String>) -> Self {
+        ClientInfo { ip, user_agent }
+    }
+
+    /// 从[http::request::Parts]中提取客户端信息
+    /// ip由[ClientIp::from_parts]解析：只有请求扩展中配置了[http_utils::client_ip::TrustedProxies]时才使用代理头，
+    /// 否则只使用直连地址([axum::extract::ConnectInfo])，见[http_utils::client_ip]
+    pub fn from_parts(parts: &Parts) -> Self {
+        let ip = ClientIp::from_parts(parts).map(|ip| ip.to_string());
+        let user_agent = parts
+            .headers
+            .get(http::header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        ClientInfo { ip, user_agent }
+    }
+}
+
+/// 授权审计观察者
+/// 所有方法都有空的默认实现，使用者只需实现关心的事件
+pub trait JwtAuditObserver: Send + Sync {
+    /// 授权成功
+    fn on_authorize(&self, _token_id: &str, _client: &ClientInfo) {}
+
+    /// 授权失败
+    fn on_authorize_failure(&self, _kind: AuthErrorKind, _client: &ClientInfo) {}
+
+    /// 代理授权成功，[actor]为真实的操作者
+    fn on_delegate(&self, _token_id: &str, _actor: &Actor, _client: &ClientInfo) {}
+
+    /// 重新认证，提升会话的认证等级
+    fn on_step_up(&self, _token_id: &str, _client: &ClientInfo) {}
+
+    /// 校验成功，代理授权的token会同时给出真实的操作者
+    fn on_verify_success(&self, _token_id: &str, _actor: Option<&Actor>, _client: &ClientInfo) {}
+
+    /// 校验失败，如果token无法解码，则`token_id`为[None]
+    fn on_verify_failure(&self, _token_id: Option<&str>, _kind: AuthErrorKind, _client: &ClientInfo) {}
+
+    /// 删除授权，`removed`表示存储中是否确实存在该授权
+    fn on_remove(&self, _token_id: &str, _removed: bool, _client: &ClientInfo) {}
+}
+
+/// 不做任何事情的观察者
+pub struct NoopAuditObserver;
+
+impl JwtAuditObserver for NoopAuditObserver {}
+
+/// 以`tracing`事件输出审计日志，target为`jwt::audit`
+pub struct TracingAuditObserver;
+
+impl JwtAuditObserver for TracingAuditObserver {
+    fn on_authorize(&self, token_id: &str, client: &ClientInfo) {
+        tracing::info!(
+            target: "jwt::audit",
+            event = "authorize",
+            token_id,
+            ip = client.ip.as_deref(),
+            user_agent = client.user_agent.as_deref(),
+        );
+    }
+
+    fn on_authorize_failure(&self, kind: AuthErrorKind, client: &ClientInfo) {
+        tracing::warn!(
+            target: "jwt::audit",
+            event = "authorize_failure",
+            kind = ?kind,
+            ip = client.ip.as_deref(),
+            user_agent = client.user_agent.as_deref(),
+        );
+    }
+
+    fn on_delegate(&self, token_id: &str, actor: &Actor, client: &ClientInfo) {
+        tracing::info!(
+            target: "jwt::audit",
+            event = "delegate",
+            token_id,
+            actor = %actor,
+            ip = client.ip.as_deref(),
+            user_agent = client.user_agent.as_deref(),
+        );
+    }
+
+    fn on_step_up(&self, token_id: &str, client: &ClientInfo) {
+        tracing::info!(
+            target: "jwt::audit",
+            event = "step_up",
+            token_id,
+            ip = client.ip.as_deref(),
+            user_agent = client.user_agent.as_deref(),
+        );
+    }
+
+    fn on_verify_success(&self, token_id: &str, actor: Option<&Actor>, client: &ClientInfo) {
+        tracing::debug!(
+            target: "jwt::audit",
+            event = "verify",
+            token_id,
+            actor = actor.map(tracing::field::display),
+            ip = client.ip.as_deref(),
+            user_agent = client.user_agent.as_deref(),
+        );
+    }
+
+    fn on_verify_failure(&self, token_id: Option<&str>, kind: AuthErrorKind, client: &ClientInfo) {
+        tracing::warn!(
+            target: "jwt::audit",
+            event = "verify_failure",
+            token_id,
+            kind = ?kind,
+            ip = client.ip.as_deref(),
+            user_agent = client.user_agent.as_deref(),
+        );
+    }
+
+    fn on_remove(&self, token_id: &str, removed: bool, client: &ClientInfo) {
+        tracing::info!(
+            target: "jwt::audit",
+            event = "remove",
+            token_id,
+            removed,
+            ip = client.ip.as_deref(),
+            user_agent = client.user_agent.as_deref(),
+        );
+    }
+}
@@ -1,5 +1,9 @@
 pub mod hmac_auth_provider;
 pub use hmac_auth_provider::HmacAuthProvider;
+pub mod rotating_hmac_auth_provider;
+pub use rotating_hmac_auth_provider::RotatingHmacAuthProvider;
+pub mod signer_auth_provider;
+pub use signer_auth_provider::{JwtSigner, SignerAuthProvider, SignerError};
 
 /// 提供
 pub trait JwtAuthProvider<Data: serde::Serialize> {
@@ -11,4 +15,63 @@ pub trait JwtAuthProvider<Data: serde::Serialize> {
     /// 将token解码成载荷
     /// 注意，解码时不应该进行有效性检查，应该由调用者自行检查
     fn decode(&self, jwt: &str) -> Result<Data, Self::Error>;
-}
\ No newline at end of file
+}
+
+/// 异步的[JwtAuthProvider]，用于密钥不在进程内的情况，如KMS/HSM，见[SignerAuthProvider]
+/// [JwtProvider](crate::jwt_provider::JwtProvider)使用此trait，[JwtAuthProvider]的实现都自动实现了此trait
+pub trait JwtAsyncAuthProvider<Data: serde::Serialize> {
+    type Error;
+
+    /// 将载荷编码成token
+    fn encode(&self, payload: &Data) -> impl Future<Output = Result<String, Self::Error>>;
+
+    /// 将token解码成载荷，与[JwtAuthProvider::decode]一样不进行有效性检查
+    fn decode(&self, jwt: &str) -> impl Future<Output = Result<Data, Self::Error>>;
+}
+
+/// 返回的Future都是`Send`的[JwtAsyncAuthProvider]，用于需要在多线程运行时中执行的场景，如[AuthRouter](crate::jwt_auth_router::AuthRouter)
+/// 实现此trait即自动实现[JwtAsyncAuthProvider]，[JwtAuthProvider]的实现在载荷为`Send`时都自动实现了此trait
+pub trait SendJwtAsyncAuthProvider<Data: serde::Serialize> {
+    type Error;
+
+    /// 见[JwtAsyncAuthProvider::encode]
+    fn encode(&self, payload: &Data) -> impl Future<Output = Result<String, Self::Error>> + Send;
+
+    /// 见[JwtAsyncAuthProvider::decode]
+    fn decode(&self, jwt: &str) -> impl Future<Output = Result<Data, Self::Error>> + Send;
+}
+
+impl<Data, T> JwtAsyncAuthProvider<Data> for T
+where
+    Data: serde::Serialize,
+    T: SendJwtAsyncAuthProvider<Data>,
+{
+    type Error = T::Error;
+
+    fn encode(&self, payload: &Data) -> impl Future<Output = Result<String, Self::Error>> {
+        SendJwtAsyncAuthProvider::encode(self, payload)
+    }
+
+    fn decode(&self, jwt: &str) -> impl Future<Output = Result<Data, Self::Error>> {
+        SendJwtAsyncAuthProvider::decode(self, jwt)
+    }
+}
+
+/// 同步授权器都可以直接用于[JwtProvider](crate::jwt_provider::JwtProvider)与[AuthRouter](crate::jwt_auth_router::AuthRouter)，
+/// 要求载荷与错误类型是`Send`的
+impl<Data, AuthProvider> SendJwtAsyncAuthProvider<Data> for AuthProvider
+where
+    Data: serde::Serialize + Send,
+    AuthProvider: JwtAuthProvider<Data>,
+    AuthProvider::Error: Send,
+{
+    type Error = AuthProvider::Error;
+
+    fn encode(&self, payload: &Data) -> impl Future<Output = Result<String, Self::Error>> + Send {
+        std::future::ready(JwtAuthProvider::encode(self, payload))
+    }
+
+    fn decode(&self, jwt: &str) -> impl Future<Output = Result<Data, Self::Error>> + Send {
+        std::future::ready(JwtAuthProvider::decode(self, jwt))
+    }
+}
@@ -0,0 +1,150 @@
+/// 支持密钥轮换的Hmac授权器
+/// 使用当前密钥签名，并在header中写入`kid`，校验时可以使用当前密钥与所有未过期的旧密钥，
+/// 因此轮换密钥不会使已签发的token立即失效
+/// 该授权器可以Clone，各个副本共享同一组密钥，将一个副本交给[JwtProvider](crate::jwt_provider::JwtProvider)后，
+/// 可以通过另一个副本在运行时调用[RotatingHmacAuthProvider::rotate]
+///
+use crate::jwt_auth_provider::JwtAuthProvider;
+use crate::jwt_clock::{JwtClock, SystemClock};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header};
+use serde::de::DeserializeOwned;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+struct HmacKey {
+    kid: String,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+}
+
+impl HmacKey {
+    fn new(kid: String, secret: &[u8]) -> Self {
+        HmacKey {
+            kid,
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+        }
+    }
+}
+
+struct HmacKeys {
+    current: HmacKey,
+    /// 旧密钥及其失效时间(毫秒)
+    previous: Vec<(HmacKey, i64)>,
+}
+
+#[derive(Clone)]
+pub struct RotatingHmacAuthProvider {
+    keys: Arc<RwLock<HmacKeys>>,
+    clock: Arc<dyn JwtClock>,
+}
+
+impl RotatingHmacAuthProvider {
+    /// [kid]密钥的标识，会写入token的header
+    pub fn new(kid: impl Into<String>, secret: &[u8]) -> Self {
+        RotatingHmacAuthProvider {
+            keys: Arc::new(RwLock::new(HmacKeys {
+                current: HmacKey::new(kid.into(), secret),
+                previous: Vec::new(),
+            })),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 设置时钟，用于判断旧密钥是否过期，默认使用[SystemClock]
+    pub fn with_clock(mut self, clock: impl JwtClock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// 当前密钥的标识
+    pub fn current_kid(&self) -> String {
+        self.keys.read().unwrap().current.kid.clone()
+    }
+
+    /// 仍可用于校验的旧密钥的标识
+    pub fn previous_kids(&self) -> Vec<String> {
+        let now = self.clock.now_millis();
+        let keys = self.keys.read().unwrap();
+        keys.previous
+            .iter()
+            .filter(|(_, expire_ms)| now < *expire_ms)
+            .map(|(key, _)| key.kid.clone())
+            .collect()
+    }
+
+    /// 轮换密钥，之后使用新密钥签名
+    /// 原来的密钥在[grace]内仍可用于校验，一般不应短于token的有效时长
+    /// 如果[kid]与已有的旧密钥相同，旧密钥会被替换
+    /// [kid]与当前密钥相同时不轮换并返回`false`，否则同一个kid会对应两个密钥，用旧密钥签发的token无法校验
+    pub fn rotate(&self, kid: impl Into<String>, secret: &[u8], grace: Duration) -> bool {
+        let kid = kid.into();
+        let expire_ms = self.clock.now_millis() + grace.as_millis() as i64;
+        let mut keys = self.keys.write().unwrap();
+        if keys.current.kid == kid {
+            return false;
+        }
+        let previous = std::mem::replace(&mut keys.current, HmacKey::new(kid.clone(), secret));
+        keys.previous.retain(|(key, _)| key.kid != kid && key.kid != previous.kid);
+        keys.previous.push((previous, expire_ms));
+        true
+    }
+
+    /// 立即停用旧密钥，返回该密钥是否存在
+    pub fn retire(&self, kid: &str) -> bool {
+        let mut keys = self.keys.write().unwrap();
+        let len = keys.previous.len();
+        keys.previous.retain(|(key, _)| key.kid != kid);
+        keys.previous.len() != len
+    }
+
+    /// 清理已过期的旧密钥
+    pub fn purge_expired(&self) {
+        let now = self.clock.now_millis();
+        let mut keys = self.keys.write().unwrap();
+        keys.previous.retain(|(_, expire_ms)| now < *expire_ms);
+    }
+}
+
+impl<Data: serde::Serialize + DeserializeOwned> JwtAuthProvider<Data> for RotatingHmacAuthProvider {
+    type Error = jsonwebtoken::errors::Error;
+
+    fn encode(&self, payload: &Data) -> Result<String, Self::Error> {
+        let keys = self.keys.read().unwrap();
+        let header = Header {
+            kid: Some(keys.current.kid.clone()),
+            ..Header::default()
+        };
+        jsonwebtoken::encode(&header, payload, &keys.current.encoding_key)
+    }
+
+    fn decode(&self, jwt: &str) -> Result<Data, Self::Error> {
+        let kid = jsonwebtoken::decode_header(jwt)?.kid;
+        let mut validation = jsonwebtoken::Validation::default();
+        validation.required_spec_claims.remove("exp");
+        validation.validate_exp = false;
+
+        let now = self.clock.now_millis();
+        let keys = self.keys.read().unwrap();
+        let candidates = std::iter::once(&keys.current).chain(
+            keys.previous
+                .iter()
+                .filter(|(_, expire_ms)| now < *expire_ms)
+                .map(|(key, _)| key),
+        );
+        let mut result = Err(ErrorKind::InvalidSignature.into());
+        for key in candidates {
+            // 有kid时只使用对应的密钥，没有kid时(如轮换前签发的token)依次尝试
+            if kid.as_ref().is_some_and(|kid| *kid != key.kid) {
+                continue;
+            }
+            result = jsonwebtoken::decode::<Data>(jwt, &key.decoding_key, &validation)
+                .map(|token| token.claims);
+            if result.is_ok() || kid.is_some() {
+                break;
+            }
+        }
+        result
+    }
+}
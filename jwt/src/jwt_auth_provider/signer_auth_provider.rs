@@ -0,0 +1,177 @@
+/// 使用外部签名服务的授权器
+/// 私钥保存在KMS/HSM或签名服务中，签名时通过[JwtSigner]异步请求签名，
+/// 校验时使用[JwtSigner::public_key]获取的公钥，公钥会被缓存，见[SignerAuthProvider::with_public_key_ttl]
+///
+use crate::jwt_auth_provider::SendJwtAsyncAuthProvider;
+use crate::jwt_clock::{JwtClock, SystemClock};
+use crate::jwt_payload::JwtPayload;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use jsonwebtoken::{Algorithm, DecodingKey, Header};
+use serde::de::DeserializeOwned;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// 签名器，私钥不离开外部服务
+/// 返回的Future需要是`Send`的，以便[SignerAuthProvider]用于[AuthRouter](crate::jwt_auth_router::AuthRouter)
+pub trait JwtSigner {
+    type Error;
+
+    /// 签名算法，只支持非对称算法，HS*会被[SignerAuthProvider::new]拒绝
+    fn algorithm(&self) -> Algorithm;
+
+    /// 当前密钥的标识，会写入token的header；token的kid与缓存公钥的kid不一致时会重新获取公钥
+    fn key_id(&self) -> Option<String> {
+        None
+    }
+
+    /// 对`{header}.{claims}`签名，返回JWS格式的签名(ECDSA为`r || s`，而非DER)
+    fn sign(&self, message: &[u8]) -> impl Future<Output = Result<Vec<u8>, Self::Error>> + Send;
+
+    /// 获取用于校验签名的公钥
+    fn public_key(&self) -> impl Future<Output = Result<DecodingKey, Self::Error>> + Send;
+}
+
+#[derive(Debug)]
+pub enum SignerError<Error> {
+    /// 签名服务返回的错误
+    Signer(Error),
+    /// 编码或校验token失败
+    Jwt(jsonwebtoken::errors::Error),
+}
+
+/// 未知kid触发重新获取公钥的最小间隔，避免伪造的kid频繁请求外部服务
+const MIN_REFETCH_INTERVAL_MS: i64 = 1000;
+
+struct CachedPublicKey {
+    key: DecodingKey,
+    key_id: Option<String>,
+    fetched_ms: i64,
+}
+
+pub struct SignerAuthProvider<Signer> {
+    signer: Signer,
+    public_key: RwLock<Option<CachedPublicKey>>,
+    public_key_ttl: Duration,
+    clock: Arc<dyn JwtClock>,
+}
+
+impl<Signer: JwtSigner> SignerAuthProvider<Signer> {
+    /// 签名器的算法为对称算法(HS*)时返回[jsonwebtoken::errors::ErrorKind::InvalidAlgorithm]
+    pub fn new(signer: Signer) -> Result<Self, SignerError<Signer::Error>> {
+        if matches!(
+            signer.algorithm(),
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(SignerError::Jwt(
+                jsonwebtoken::errors::ErrorKind::InvalidAlgorithm.into(),
+            ));
+        }
+        Ok(SignerAuthProvider {
+            signer,
+            public_key: RwLock::new(None),
+            public_key_ttl: Duration::from_secs(3600),
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    /// 设置公钥的缓存时间，默认1小时
+    pub fn with_public_key_ttl(mut self, ttl: Duration) -> Self {
+        self.public_key_ttl = ttl;
+        self
+    }
+
+    /// 设置时钟，用于判断公钥缓存是否过期，默认使用[SystemClock]
+    pub fn with_clock(mut self, clock: impl JwtClock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn signer(&self) -> &Signer {
+        &self.signer
+    }
+
+    /// 清除缓存的公钥，下次校验时重新获取，一般在外部服务轮换密钥后调用
+    pub fn invalidate_public_key(&self) {
+        *self.public_key.write().unwrap() = None;
+    }
+
+    /// 获取校验用的公钥，`key_id`为token的kid，与缓存公钥的kid不一致时重新获取一次
+    async fn decoding_key(
+        &self,
+        key_id: Option<&str>,
+    ) -> Result<DecodingKey, SignerError<Signer::Error>> {
+        let now = self.clock.now_millis();
+        if let Some(cached) = self.public_key.read().unwrap().as_ref() {
+            let expired = now - cached.fetched_ms >= self.public_key_ttl.as_millis() as i64;
+            let unknown_kid = key_id.is_some() && key_id != cached.key_id.as_deref();
+            if !expired && (!unknown_kid || now - cached.fetched_ms < MIN_REFETCH_INTERVAL_MS) {
+                return Ok(cached.key.clone());
+            }
+        }
+        let key = self
+            .signer
+            .public_key()
+            .await
+            .map_err(SignerError::Signer)?;
+        *self.public_key.write().unwrap() = Some(CachedPublicKey {
+            key: key.clone(),
+            key_id: self.signer.key_id(),
+            fetched_ms: now,
+        });
+        Ok(key)
+    }
+}
+
+/// 只为[JwtPayload]实现，以免与同步授权器的blanket实现冲突
+impl<PayloadType, Signer> SendJwtAsyncAuthProvider<JwtPayload<PayloadType>>
+    for SignerAuthProvider<Signer>
+where
+    PayloadType: serde::Serialize + DeserializeOwned + Send,
+    Signer: JwtSigner + Sync,
+    Signer::Error: Send,
+{
+    type Error = SignerError<Signer::Error>;
+
+    fn encode(
+        &self,
+        payload: &JwtPayload<PayloadType>,
+    ) -> impl Future<Output = Result<String, Self::Error>> + Send {
+        let header = Header {
+            kid: self.signer.key_id(),
+            ..Header::new(self.signer.algorithm())
+        };
+        // 载荷在签名前序列化，异步部分只持有签名器
+        let message = serde_json::to_vec(&header)
+            .and_then(|header| Ok((header, serde_json::to_vec(payload)?)))
+            .map(|(header, claims)| {
+                format!(
+                    "{}.{}",
+                    URL_SAFE_NO_PAD.encode(header),
+                    URL_SAFE_NO_PAD.encode(claims)
+                )
+            })
+            .map_err(|e| SignerError::Jwt(e.into()));
+        async move {
+            let message = message?;
+            let signature = self
+                .signer
+                .sign(message.as_bytes())
+                .await
+                .map_err(SignerError::Signer)?;
+            Ok(format!("{message}.{}", URL_SAFE_NO_PAD.encode(signature)))
+        }
+    }
+
+    async fn decode(&self, jwt: &str) -> Result<JwtPayload<PayloadType>, Self::Error> {
+        let header = jsonwebtoken::decode_header(jwt).map_err(SignerError::Jwt)?;
+        let key = self.decoding_key(header.kid.as_deref()).await?;
+        let mut validation = jsonwebtoken::Validation::new(self.signer.algorithm());
+        validation.required_spec_claims.remove("exp");
+        validation.validate_exp = false;
+
+        jsonwebtoken::decode::<JwtPayload<PayloadType>>(jwt, &key, &validation)
+            .map(|token| token.claims)
+            .map_err(SignerError::Jwt)
+    }
+}
@@ -0,0 +1,272 @@
+/// 现成的授权路由
+/// 围绕[JwtBearerProvider]提供登录，登出，续期与查询当前用户的接口，响应统一使用[http_utils::response::Response]：
+/// - `POST /login`: 请求体为凭证(json)，由[CredentialChecker]校验，成功时返回[AuthBody]
+/// - `POST /logout`: 删除请求中bear token对应的授权
+/// - `POST /refresh`: 以请求中的bear token续期，返回新的[AuthBody]
+/// - `GET /me`: 返回请求中bear token对应的[JwtPayload]
+///
+/// 失败时`code`为http状态码，`message`为错误信息
+/// 登录以客户端ip(见[crate::jwt_audit::ClientInfo::from_parts])与[AuthRouter::with_login_subject]指定的用户标识限流
+/// 接口运行在多线程运行时中，存储与授权器需要实现[SendJwtStorageProvider]与[SendJwtAsyncAuthProvider]
+///
+use crate::jwt_auth_provider::{JwtAsyncAuthProvider, SendJwtAsyncAuthProvider};
+use crate::jwt_bear_provider::{BearAuthError, JwtBearerProvider, internal_error, retry_after_secs};
+use crate::jwt_payload::{AuthBody, JwtPayload, SessionMetadata};
+use crate::jwt_rate_limiter::RateLimitKey;
+use crate::jwt_storage_provider::{JwtStorageProvider, SendJwtStorageProvider};
+use axum::body::Bytes;
+use axum::routing::{get, post};
+use axum_core::response::{IntoResponse, Response};
+use http::StatusCode;
+use http::request::Parts;
+use http_utils::api_error::ApiError;
+use http_utils::response::Response as Envelope;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// 登录失败的原因
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoginError {
+    /// 凭证错误，会计入限流
+    InvalidCredentials,
+    /// 凭证正确但不允许登录，如账号被禁用
+    Forbidden(String),
+    Internal(String),
+}
+
+impl LoginError {
+    fn status_and_message(&self) -> (StatusCode, String) {
+        match self {
+            LoginError::InvalidCredentials => {
+                (StatusCode::UNAUTHORIZED, "invalid credentials".to_string())
+            }
+            LoginError::Forbidden(message) => (StatusCode::FORBIDDEN, message.clone()),
+            LoginError::Internal(message) => (StatusCode::INTERNAL_SERVER_ERROR, message.clone()),
+        }
+    }
+}
+
+/// 校验登录凭证，成功时返回写入token的载荷
+/// 实现了`Fn(Credentials) -> impl Future<Output = Result<PayloadType, LoginError>>`的闭包可以直接使用
+pub trait CredentialChecker<PayloadType, Credentials>: Send + Sync + 'static {
+    fn check(
+        &self,
+        credentials: Credentials,
+    ) -> impl Future<Output = Result<PayloadType, LoginError>> + Send;
+}
+
+impl<F, Fut, PayloadType, Credentials> CredentialChecker<PayloadType, Credentials> for F
+where
+    F: Fn(Credentials) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<PayloadType, LoginError>> + Send,
+{
+    fn check(
+        &self,
+        credentials: Credentials,
+    ) -> impl Future<Output = Result<PayloadType, LoginError>> + Send {
+        self(credentials)
+    }
+}
+
+type SessionFn<PayloadType> = dyn Fn(&PayloadType) -> SessionMetadata + Send + Sync;
+
+type SubjectFn<Credentials> = dyn Fn(&Credentials) -> String + Send + Sync;
+
+pub struct AuthRouter<
+    JwtAuthProviderType,
+    JwtStorageProviderType,
+    PayloadType,
+    Credentials,
+    Checker,
+> {
+    bearer: Arc<JwtBearerProvider<JwtAuthProviderType, JwtStorageProviderType>>,
+    checker: Arc<Checker>,
+    session: Option<Arc<SessionFn<PayloadType>>>,
+    subject: Option<Arc<SubjectFn<Credentials>>>,
+    _marker: PhantomData<fn(Credentials)>,
+}
+
+impl<JwtAuthProviderType, JwtStorageProviderType, PayloadType, Credentials, Checker>
+    AuthRouter<JwtAuthProviderType, JwtStorageProviderType, PayloadType, Credentials, Checker>
+where
+    JwtAuthProviderType:
+        SendJwtAsyncAuthProvider<JwtPayload<PayloadType>> + Send + Sync + 'static,
+    <JwtAuthProviderType as SendJwtAsyncAuthProvider<JwtPayload<PayloadType>>>::Error: Debug + Send,
+    JwtStorageProviderType: SendJwtStorageProvider + Send + Sync + 'static,
+    <JwtStorageProviderType as SendJwtStorageProvider>::Error: Debug + Send,
+    PayloadType: Serialize + PartialEq + Send + Sync + 'static,
+    Credentials: DeserializeOwned + Send + 'static,
+    Checker: CredentialChecker<PayloadType, Credentials>,
+{
+    pub fn new(
+        bearer: Arc<JwtBearerProvider<JwtAuthProviderType, JwtStorageProviderType>>,
+        checker: Checker,
+    ) -> Self {
+        AuthRouter {
+            bearer,
+            checker: Arc::new(checker),
+            session: None,
+            subject: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// 登录时根据载荷生成会话信息(如`subject`)，ip与user agent会自动从请求中获取
+    pub fn with_session_metadata(
+        mut self,
+        session: impl Fn(&PayloadType) -> SessionMetadata + Send + Sync + 'static,
+    ) -> Self {
+        self.session = Some(Arc::new(session));
+        self
+    }
+
+    /// 登录时除客户端ip外，同时以凭证中的用户标识(如用户名)为键限流，防止从多个ip猜测同一个账号的密码
+    /// 被锁定期间该账号无法登录
+    pub fn with_login_subject(
+        mut self,
+        subject: impl Fn(&Credentials) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.subject = Some(Arc::new(subject));
+        self
+    }
+
+    pub fn build<State: Clone + Send + Sync + 'static>(self) -> axum::Router<State> {
+        let login = {
+            let bearer = self.bearer.clone();
+            let checker = self.checker.clone();
+            let session = self.session.clone();
+            let subject = self.subject.clone();
+            move |parts: Parts, body: Bytes| async move {
+                let checker = checker.as_ref();
+                login(&bearer, checker, session.as_deref(), subject.as_deref(), parts, body).await
+            }
+        };
+        let logout = {
+            let bearer = self.bearer.clone();
+            move |mut parts: Parts| async move {
+                let payload = match bearer.verify::<PayloadType>(&mut parts).await {
+                    Ok(payload) => payload,
+                    Err(e) => return bear_error(e),
+                };
+                match bearer
+                    .remove::<PayloadType>(&payload.token_id, &parts)
+                    .await
+                {
+                    Ok(_) => Envelope::<()>::success(None).into_response(),
+                    Err(e) => {
+                        tracing::error!(error = ?e, "failed to remove jwt session");
+                        let (status, message) = internal_error();
+                        fail(status, message)
+                    }
+                }
+            }
+        };
+        let refresh = {
+            let bearer = self.bearer.clone();
+            move |mut parts: Parts| async move {
+                match bearer.refresh::<PayloadType>(&mut parts).await {
+                    Ok(body) => Envelope::success(Some(body)).into_response(),
+                    Err(e) => bear_error(e),
+                }
+            }
+        };
+        let me = {
+            let bearer = self.bearer.clone();
+            move |mut parts: Parts| async move {
+                match bearer.verify::<PayloadType>(&mut parts).await {
+                    Ok(payload) => Envelope::success(Some(payload)).into_response(),
+                    Err(e) => bear_error(e),
+                }
+            }
+        };
+        axum::Router::new()
+            .route("/login", post(login))
+            .route("/logout", post(logout))
+            .route("/refresh", post(refresh))
+            .route("/me", get(me))
+    }
+}
+
+async fn login<JwtAuthProviderType, JwtStorageProviderType, PayloadType, Credentials>(
+    bearer: &JwtBearerProvider<JwtAuthProviderType, JwtStorageProviderType>,
+    checker: &impl CredentialChecker<PayloadType, Credentials>,
+    session: Option<&SessionFn<PayloadType>>,
+    subject: Option<&SubjectFn<Credentials>>,
+    parts: Parts,
+    body: Bytes,
+) -> Response
+where
+    JwtAuthProviderType: JwtAsyncAuthProvider<JwtPayload<PayloadType>>,
+    <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<PayloadType>>>::Error: Debug,
+    JwtStorageProviderType: JwtStorageProvider,
+    <JwtStorageProviderType as JwtStorageProvider>::Error: Debug,
+    PayloadType: Serialize + PartialEq,
+    Credentials: DeserializeOwned,
+{
+    let credentials = match serde_json::from_slice::<Credentials>(&body) {
+        Ok(credentials) => credentials,
+        Err(e) => return fail(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+    // ip由可信代理解析，客户端无法通过代理头伪造
+    let client = crate::jwt_audit::ClientInfo::from_parts(&parts);
+    let limit_keys: Vec<RateLimitKey> = client
+        .ip
+        .map(RateLimitKey::Ip)
+        .into_iter()
+        .chain(subject.map(|subject| RateLimitKey::Subject(subject(&credentials))))
+        .collect();
+    for key in &limit_keys {
+        if let Err(e) = bearer.check_rate_limit::<
+            <JwtStorageProviderType as JwtStorageProvider>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<PayloadType>>>::Error,
+        >(key)
+        {
+            return bear_error(e);
+        }
+    }
+    let payload = match checker.check(credentials).await {
+        Ok(payload) => payload,
+        Err(e) => {
+            if e == LoginError::InvalidCredentials {
+                limit_keys.iter().for_each(|key| bearer.record_failure(key));
+            }
+            let (status, message) = e.status_and_message();
+            return fail(status, message);
+        }
+    };
+    // 只清除用户名的失败记录，ip可能被多个用户共享，其失败记录随窗口过期
+    limit_keys
+        .iter()
+        .filter(|key| matches!(key, RateLimitKey::Subject(_)))
+        .for_each(|key| bearer.record_success(key));
+    let metadata = session.map(|session| session(&payload)).unwrap_or_default();
+    match bearer
+        .authorize_with_session(payload, metadata, &parts)
+        .await
+    {
+        Ok(body) => Envelope::<AuthBody>::success(Some(body)).into_response(),
+        Err(e) => bear_error(BearAuthError::AuthError(e)),
+    }
+}
+
+fn fail(status: StatusCode, message: String) -> Response {
+    ApiError::from_status(status, message).into_response()
+}
+
+fn bear_error<StorageError: Debug, DecodeError: Debug>(
+    e: BearAuthError<StorageError, DecodeError>,
+) -> Response {
+    let (status, message) = e.status_and_message();
+    let mut response = fail(status, message);
+    if let Some(retry_after) = e.retry_after()
+        && let Ok(value) = http::HeaderValue::from_str(&retry_after_secs(retry_after))
+    {
+        response
+            .headers_mut()
+            .insert(http::header::RETRY_AFTER, value);
+    }
+    response
+}
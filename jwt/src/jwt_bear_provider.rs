@@ -3,54 +3,107 @@
 ///
 ///
 use axum_core::response::{IntoResponse, Response};
+use http_utils::token_source::TokenError;
 use http_utils::utils::get_bear_token;
-use crate::jwt_auth_provider::JwtAuthProvider;
-use crate::jwt_payload::JwtPayload;
-use crate::jwt_provider::{AuthBody, AuthError, JwtProvider};
+use crate::jwt_audit::{ClientInfo, JwtAuditObserver};
+use crate::jwt_auth_provider::JwtAsyncAuthProvider;
+use crate::jwt_payload::{AuthContext, JwtPayload, SessionMetadata};
+use crate::jwt_provider::{AuthBody, AuthError, AuthErrorKind, JwtProvider};
+use crate::jwt_rate_limiter::{JwtRateLimiter, RateLimitKey, warn_missing_client_ip};
 use crate::jwt_storage_provider::JwtStorageProvider;
 use http::request::Parts;
 use http::StatusCode;
+use std::sync::Arc;
+use std::time::Duration;
 
 pub enum BearAuthError<StorageError, DecodeError> {
-    BearError(String),
+    /// 请求中没有令牌或格式错误
+    BearError(TokenError),
     AuthError(AuthError<StorageError, DecodeError>),
+    /// 失败次数过多，需要等待指定时间后重试
+    TooManyAttempts(Duration),
 }
 
-impl<StorageError: std::fmt::Debug, DecodeError: std::fmt::Debug> IntoResponse for BearAuthError<StorageError, DecodeError> {
-    fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            BearAuthError::BearError(e) => {
-                (StatusCode::BAD_REQUEST, e)
+impl<StorageError: std::fmt::Debug, DecodeError: std::fmt::Debug>
+    BearAuthError<StorageError, DecodeError>
+{
+    /// 对应的http状态码与错误信息
+    pub fn status_and_message(&self) -> (StatusCode, String) {
+        match self {
+            BearAuthError::BearError(e @ TokenError::Missing(_)) => {
+                (StatusCode::UNAUTHORIZED, e.to_string())
             }
-            BearAuthError::AuthError(auth_error) => {
-                match auth_error {
-                    AuthError::StorageError(e) => {
-                        (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:?}"))
-                    }
-                    AuthError::DecodeError(e) => {
-                        (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:?}"))
-                    }
-                    AuthError::OutOfDate => {
-                        (StatusCode::UNAUTHORIZED, "token expired".to_string())
-                    }
-                    AuthError::NoAuthDataFound => {
-                        (StatusCode::UNAUTHORIZED, "not login".to_string())
-                    }
-                    AuthError::AuthDataNotMatch => {
-                        (StatusCode::UNAUTHORIZED, "token not match".to_string())
-                    }
+            BearAuthError::BearError(e) => (StatusCode::BAD_REQUEST, e.to_string()),
+            BearAuthError::TooManyAttempts(_) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "too many attempts".to_string(),
+            ),
+            BearAuthError::AuthError(auth_error) => match auth_error {
+                // 内部错误只记录日志，不把细节返回给客户端
+                AuthError::StorageError(e) => {
+                    tracing::error!(error = ?e, "jwt storage error");
+                    internal_error()
                 }
-            }
-        };
+                AuthError::DecodeError(e) => {
+                    tracing::error!(error = ?e, "jwt decode error");
+                    internal_error()
+                }
+                AuthError::OutOfDate => (StatusCode::UNAUTHORIZED, "token expired".to_string()),
+                AuthError::NoAuthDataFound => (StatusCode::UNAUTHORIZED, "not login".to_string()),
+                AuthError::AuthDataNotMatch => {
+                    (StatusCode::UNAUTHORIZED, "token not match".to_string())
+                }
+                AuthError::ImpersonationForbidden => {
+                    (StatusCode::FORBIDDEN, "impersonation forbidden".to_string())
+                }
+            },
+        }
+    }
+
+    /// 需要等待的时间，用于`Retry-After`
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            BearAuthError::TooManyAttempts(retry_after) => Some(*retry_after),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn internal_error() -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "internal server error".to_string(),
+    )
+}
+
+impl<StorageError: std::fmt::Debug, DecodeError: std::fmt::Debug> IntoResponse
+    for BearAuthError<StorageError, DecodeError>
+{
+    fn into_response(self) -> Response {
+        let (status, error_message) = self.status_and_message();
         let body = axum::Json(serde_json::json!({
             "error": error_message,
         }));
-        (status, body).into_response()
+        match self.retry_after() {
+            Some(retry_after) => (
+                status,
+                [(http::header::RETRY_AFTER, retry_after_secs(retry_after))],
+                body,
+            )
+                .into_response(),
+            None => (status, body).into_response(),
+        }
     }
 }
 
+/// Retry-After以秒为单位，向上取整
+pub(crate) fn retry_after_secs(retry_after: Duration) -> String {
+    retry_after.as_millis().div_ceil(1000).max(1).to_string()
+}
+
 pub struct JwtBearerProvider<JwtAuthProviderType, JwtStorageProviderType> {
     jwt_provider: JwtProvider<JwtAuthProviderType, JwtStorageProviderType>,
+    rate_limiter: Option<Arc<dyn JwtRateLimiter>>,
 }
 
 impl<JwtAuthProviderType, JwtStorageProviderType>
@@ -64,7 +117,65 @@ where
         storage_provider: JwtStorageProviderType,
     ) -> Self {
         let jwt_provider = JwtProvider::new(expire_in_ms, auth_provider, storage_provider);
-        JwtBearerProvider { jwt_provider }
+        JwtBearerProvider {
+            jwt_provider,
+            rate_limiter: None,
+        }
+    }
+
+    /// 设置审计观察者，见[JwtProvider::with_observer]
+    pub fn with_observer(self, observer: impl JwtAuditObserver + 'static) -> Self {
+        JwtBearerProvider {
+            jwt_provider: self.jwt_provider.with_observer(observer),
+            rate_limiter: self.rate_limiter,
+        }
+    }
+
+    /// 设置签发者，见[JwtProvider::with_issuer]
+    pub fn with_issuer(self, issuer: impl Into<String>) -> Self {
+        JwtBearerProvider {
+            jwt_provider: self.jwt_provider.with_issuer(issuer),
+            rate_limiter: self.rate_limiter,
+        }
+    }
+
+    /// 设置限流器，设置后[Self::verify]会以客户端ip为键记录失败次数，并拒绝被锁定的客户端，
+    /// 校验成功不会清除失败记录，失败记录随窗口过期
+    /// 客户端ip由[ClientInfo::from_parts]解析，只有配置了可信代理时才使用代理头，客户端无法伪造；
+    /// 获取不到ip(没有使用`into_make_service_with_connect_info`启动服务)时不限流，并输出一次警告
+    pub fn with_rate_limiter(mut self, rate_limiter: impl JwtRateLimiter + 'static) -> Self {
+        self.rate_limiter = Some(Arc::new(rate_limiter));
+        self
+    }
+
+    /// 内部的[JwtProvider]
+    pub fn jwt_provider(&self) -> &JwtProvider<JwtAuthProviderType, JwtStorageProviderType> {
+        &self.jwt_provider
+    }
+
+    /// 检查指定键是否被限流，可用于登录等场景(如以用户名为键)
+    pub fn check_rate_limit<StorageError, DecodeError>(
+        &self,
+        key: &RateLimitKey,
+    ) -> Result<(), BearAuthError<StorageError, DecodeError>> {
+        match &self.rate_limiter {
+            Some(limiter) => limiter.check(key).map_err(BearAuthError::TooManyAttempts),
+            None => Ok(()),
+        }
+    }
+
+    /// 记录一次失败，如登录时密码错误
+    pub fn record_failure(&self, key: &RateLimitKey) {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.record_failure(key);
+        }
+    }
+
+    /// 记录一次成功，如登录成功，见[JwtRateLimiter::record_success]
+    pub fn record_success(&self, key: &RateLimitKey) {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.record_success(key);
+        }
     }
 
     pub async fn authorize<JwtPayloadType>(
@@ -74,16 +185,59 @@ where
         AuthBody,
         AuthError<
             <JwtStorageProviderType as JwtStorageProvider>::Error,
-            <JwtAuthProviderType as JwtAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
         >,
     >
     where
         JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
-        JwtAuthProviderType: super::jwt_auth_provider::JwtAuthProvider<JwtPayload<JwtPayloadType>>,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
     {
         self.jwt_provider.authorize(payload).await
     }
 
+    /// 授权，并从[http::request::Parts]中提取客户端信息用于审计
+    pub async fn authorize_with_parts<JwtPayloadType>(
+        &self,
+        payload: JwtPayloadType,
+        parts: &Parts,
+    ) -> Result<
+        AuthBody,
+        AuthError<
+            <JwtStorageProviderType as JwtStorageProvider>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+        >,
+    >
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        let client = ClientInfo::from_parts(parts);
+        self.jwt_provider.authorize_with_client(payload, &client).await
+    }
+
+    /// 授权并保存会话信息，未设置的ip, user_agent从请求中获取
+    pub async fn authorize_with_session<JwtPayloadType>(
+        &self,
+        payload: JwtPayloadType,
+        session: SessionMetadata,
+        parts: &Parts,
+    ) -> Result<
+        AuthBody,
+        AuthError<
+            <JwtStorageProviderType as JwtStorageProvider>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+        >,
+    >
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        let client = ClientInfo::from_parts(parts);
+        self.jwt_provider
+            .authorize_with_session(payload, AuthContext::default(), session, &client)
+            .await
+    }
+
     /// 从 [http::request::Parts]中提取bear数据，并进行检验
     /// 如果检验成功，则返回token对应的payload数据
     pub async fn verify<JwtPayloadType>(
@@ -93,23 +247,124 @@ where
         JwtPayload<JwtPayloadType>,
         BearAuthError<
             <JwtStorageProviderType as JwtStorageProvider>::Error,
-            <JwtAuthProviderType as JwtAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
         >,
     >
     where
         JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
-        JwtAuthProviderType: super::jwt_auth_provider::JwtAuthProvider<JwtPayload<JwtPayloadType>>,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
     {
+        let client = ClientInfo::from_parts(parts);
+        let limit_key = client.ip.clone().map(RateLimitKey::Ip);
+        if limit_key.is_none() && self.rate_limiter.is_some() {
+            warn_missing_client_ip();
+        }
+        if let Some(key) = &limit_key
+            && let Err(e) = self.check_rate_limit(key)
+        {
+            self.jwt_provider
+                .observer()
+                .on_verify_failure(None, AuthErrorKind::RateLimited, &client);
+            return Err(e);
+        }
         let token = get_bear_token(parts).await;
         match token {
             Ok(token) => {
-                let ret = self.jwt_provider.verify::<JwtPayloadType>(&token).await;
+                let ret = self
+                    .jwt_provider
+                    .verify_with_client::<JwtPayloadType>(&token, &client)
+                    .await;
                 match ret {
                     Ok(ret) => Ok(ret),
-                    Err(e) => Err(BearAuthError::AuthError(e)),
+                    Err(e) => {
+                        // 过期和存储错误不视为攻击
+                        if let Some(key) = &limit_key
+                            && matches!(
+                                e.kind(),
+                                AuthErrorKind::DecodeError
+                                    | AuthErrorKind::NoAuthDataFound
+                                    | AuthErrorKind::AuthDataNotMatch
+                            )
+                        {
+                            self.record_failure(key);
+                        }
+                        Err(BearAuthError::AuthError(e))
+                    }
                 }
             }
-            Err(e) => Err(BearAuthError::BearError(format!("{:?}", e))),
+            Err(e) => {
+                self.jwt_provider
+                    .observer()
+                    .on_verify_failure(None, AuthErrorKind::InvalidBearer, &client);
+                Err(BearAuthError::BearError(e))
+            }
         }
     }
+
+    /// 以请求中的bear token续期，见[JwtProvider::refresh_with_client]
+    pub async fn refresh<JwtPayloadType>(
+        &self,
+        parts: &mut Parts,
+    ) -> Result<
+        AuthBody,
+        BearAuthError<
+            <JwtStorageProviderType as JwtStorageProvider>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+        >,
+    >
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        let client = ClientInfo::from_parts(parts);
+        let token = get_bear_token(parts)
+            .await
+            .map_err(BearAuthError::BearError)?;
+        self.jwt_provider
+            .refresh_with_client::<JwtPayloadType>(&token, &client)
+            .await
+            .map_err(BearAuthError::AuthError)
+    }
+
+    /// 用户重新认证后，提升请求中bear token对应会话的认证等级，见[JwtProvider::step_up]
+    pub async fn step_up<JwtPayloadType>(
+        &self,
+        parts: &mut Parts,
+        context: AuthContext,
+    ) -> Result<
+        AuthBody,
+        BearAuthError<
+            <JwtStorageProviderType as JwtStorageProvider>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+        >,
+    >
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        let client = ClientInfo::from_parts(parts);
+        let token = get_bear_token(parts)
+            .await
+            .map_err(BearAuthError::BearError)?;
+        self.jwt_provider
+            .step_up::<JwtPayloadType>(&token, context, &client)
+            .await
+            .map_err(BearAuthError::AuthError)
+    }
+
+    /// 删除授权，并从[http::request::Parts]中提取客户端信息用于审计
+    pub async fn remove<JwtPayloadType>(
+        &self,
+        token_id: &str,
+        parts: &Parts,
+    ) -> Result<Option<AuthBody>, <JwtStorageProviderType as JwtStorageProvider>::Error>
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        let client = ClientInfo::from_parts(parts);
+        self.jwt_provider
+            .remove_with_client::<JwtPayloadType>(token_id, &client)
+            .await
+    }
 }
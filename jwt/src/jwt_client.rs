@@ -0,0 +1,374 @@
+/// 客户端的token管理
+/// 保存当前的[AuthBody]，并通过[scheduler::scheduler::Scheduler]在过期前自动刷新
+/// 同时发起的多个刷新请求会被合并为一次
+/// 过期前刷新失败时按指数退避重试，直到过期，见[TokenManagerBuilder::with_retry_backoff]
+/// 会话结束时(登出，刷新失败，过期)会通过[channel::async_channel]通知监听者
+///
+/// 可以同时用于原生平台与wasm平台(wasm平台需要启用feature: dioxus)
+///
+use crate::jwt_clock::{JwtClock, SystemClock};
+use crate::jwt_payload::AuthBody;
+use async_runtime::MaybeSend;
+use channel::async_channel::{
+    OneshotSender, UnboundedReceiver, UnboundedSender, oneshot, unbounded,
+};
+use scheduler::ScheduleId;
+use scheduler::scheduler::Scheduler;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+use types::Duration;
+
+/// 用当前的token换取新的token，一般是向服务端发起刷新请求
+pub trait TokenRefresher: Send + Sync + 'static {
+    type Error: Clone + std::fmt::Debug + Send + 'static;
+
+    fn refresh(
+        &self,
+        current: &AuthBody,
+    ) -> impl Future<Output = Result<AuthBody, Self::Error>> + MaybeSend;
+}
+
+#[derive(Clone, Debug)]
+pub enum SessionEvent<E> {
+    /// token已经刷新
+    Refreshed(AuthBody),
+    /// 会话结束
+    Ended(SessionEndReason<E>),
+}
+
+#[derive(Clone, Debug)]
+pub enum SessionEndReason<E> {
+    /// 主动登出
+    Logout,
+    /// token已过期
+    Expired,
+    /// token过期前刷新失败
+    RefreshFailed(E),
+}
+
+#[derive(Clone, Debug)]
+pub enum RefreshError<E> {
+    /// 当前没有会话
+    NoSession,
+    /// 刷新期间会话被替换或登出
+    Canceled,
+    Refresh(E),
+}
+
+type RefreshWaiter<E> = OneshotSender<Result<AuthBody, RefreshError<E>>>;
+
+#[derive(Clone, Copy)]
+enum ScheduledAction {
+    Refresh,
+    Expire,
+}
+
+struct State<E> {
+    auth: Option<AuthBody>,
+    /// 每次会话发生变化(设置，刷新，登出)时递增，用于识别过时的定时任务与刷新结果
+    generation: u64,
+    schedule_id: Option<ScheduleId>,
+    /// 当前token连续刷新失败的次数，用于计算重试间隔
+    retries: u32,
+    /// 正在刷新时，等待刷新结果的调用者
+    refreshing: Option<Vec<RefreshWaiter<E>>>,
+    listeners: Vec<UnboundedSender<SessionEvent<E>>>,
+}
+
+struct Inner<R: TokenRefresher> {
+    refresher: R,
+    refresh_ahead: Duration,
+    retry_backoff: (Duration, Duration),
+    scheduler: Scheduler,
+    clock: Arc<dyn JwtClock>,
+    state: Mutex<State<R::Error>>,
+}
+
+pub struct TokenManager<R: TokenRefresher> {
+    inner: Arc<Inner<R>>,
+}
+
+impl<R: TokenRefresher> Clone for TokenManager<R> {
+    fn clone(&self) -> Self {
+        TokenManager {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// [TokenManager]的构建器
+pub struct TokenManagerBuilder<R: TokenRefresher> {
+    refresher: R,
+    refresh_ahead: Duration,
+    retry_backoff: (Duration, Duration),
+    scheduler: Scheduler,
+    clock: Arc<dyn JwtClock>,
+}
+
+impl<R: TokenRefresher> TokenManagerBuilder<R> {
+    /// 设置时钟，用于计算刷新时间与判断token是否过期，默认使用[SystemClock]
+    pub fn with_clock(mut self, clock: impl JwtClock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// 设置刷新失败后的重试间隔，从[initial]开始每次翻倍，最多为[max]，默认1秒到60秒
+    /// 重试时间晚于token过期时间时不再重试，到期后结束会话
+    pub fn with_retry_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.retry_backoff = (initial, max.max(initial));
+        self
+    }
+
+    pub fn build(self) -> TokenManager<R> {
+        let state = State {
+            auth: None,
+            generation: 0,
+            schedule_id: None,
+            retries: 0,
+            refreshing: None,
+            listeners: Vec::new(),
+        };
+        TokenManager {
+            inner: Arc::new(Inner {
+                refresher: self.refresher,
+                refresh_ahead: self.refresh_ahead,
+                retry_backoff: self.retry_backoff,
+                scheduler: self.scheduler,
+                clock: self.clock,
+                state: Mutex::new(state),
+            }),
+        }
+    }
+}
+
+impl<R: TokenRefresher> TokenManager<R> {
+    /// [refresh_ahead]在过期前多久进行刷新，最多为token有效时长的一半
+    pub fn new(refresher: R, refresh_ahead: Duration, scheduler: Scheduler) -> Self {
+        Self::builder(refresher, refresh_ahead, scheduler).build()
+    }
+
+    /// 需要自定义时钟或重试间隔时使用，参数同[TokenManager::new]
+    pub fn builder(
+        refresher: R,
+        refresh_ahead: Duration,
+        scheduler: Scheduler,
+    ) -> TokenManagerBuilder<R> {
+        TokenManagerBuilder {
+            refresher,
+            refresh_ahead,
+            retry_backoff: (Duration::from_secs(1), Duration::from_secs(60)),
+            scheduler,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 当前的授权信息
+    pub fn current(&self) -> Option<AuthBody> {
+        self.inner.state.lock().unwrap().auth.clone()
+    }
+
+    /// 当前的token
+    pub fn token(&self) -> Option<String> {
+        self.inner
+            .state
+            .lock()
+            .unwrap()
+            .auth
+            .as_ref()
+            .map(|auth| auth.token.clone())
+    }
+
+    /// 监听会话事件
+    pub fn subscribe(&self) -> UnboundedReceiver<SessionEvent<R::Error>> {
+        let (tx, rx) = unbounded();
+        self.inner.state.lock().unwrap().listeners.push(tx);
+        rx
+    }
+
+    /// 设置新的授权信息(如登录成功后)，并安排在过期前刷新
+    pub fn set(&self, auth: AuthBody) {
+        let generation = {
+            let mut state = self.inner.state.lock().unwrap();
+            self.reset(&mut state);
+            state.auth = Some(auth.clone());
+            state.generation
+        };
+        let at = self.refresh_at(&auth);
+        self.schedule(generation, at, ScheduledAction::Refresh);
+    }
+
+    /// 登出，清除当前授权信息
+    pub fn logout(&self) {
+        let mut state = self.inner.state.lock().unwrap();
+        if state.auth.is_some() {
+            self.reset(&mut state);
+            Self::notify(&mut state, SessionEvent::Ended(SessionEndReason::Logout));
+        }
+    }
+
+    /// 立即刷新token
+    /// 如果已经有刷新正在进行，则等待其结果，而不会再次刷新
+    pub async fn refresh(&self) -> Result<AuthBody, RefreshError<R::Error>> {
+        let pending = {
+            let mut state = self.inner.state.lock().unwrap();
+            let Some(current) = state.auth.clone() else {
+                return Err(RefreshError::NoSession);
+            };
+            match state.refreshing.as_mut() {
+                Some(waiters) => {
+                    let (tx, rx) = oneshot();
+                    waiters.push(tx);
+                    Err(rx)
+                }
+                None => {
+                    state.refreshing = Some(Vec::new());
+                    Ok((current, state.generation))
+                }
+            }
+        };
+        let (current, generation) = match pending {
+            Ok(pending) => pending,
+            Err(rx) => {
+                return rx.recv().await.unwrap_or(Err(RefreshError::Canceled));
+            }
+        };
+
+        let ret = self.inner.refresher.refresh(&current).await;
+        let (ret, next) = {
+            let mut state = self.inner.state.lock().unwrap();
+            if state.generation != generation {
+                // 刷新期间会话已经变化，等待者已经在变化时被通知
+                return Err(RefreshError::Canceled);
+            }
+            let waiters = state.refreshing.take().unwrap_or_default();
+            let (ret, next) = match ret {
+                Ok(auth) => {
+                    self.reset(&mut state);
+                    state.auth = Some(auth.clone());
+                    Self::notify(&mut state, SessionEvent::Refreshed(auth.clone()));
+                    let at = self.refresh_at(&auth);
+                    (Ok(auth), Some((at, ScheduledAction::Refresh)))
+                }
+                Err(e) if self.inner.clock.now_millis() >= current.expire_ms => {
+                    self.reset(&mut state);
+                    let reason = SessionEndReason::RefreshFailed(e.clone());
+                    Self::notify(&mut state, SessionEvent::Ended(reason));
+                    (Err(RefreshError::Refresh(e)), None)
+                }
+                Err(e) => {
+                    // 还未过期，保留当前token并稍后重试，来不及重试时到期结束会话
+                    warn!("刷新token失败: {e:?}");
+                    self.cancel_scheduled(&mut state);
+                    let retry_at = self.inner.clock.now_millis()
+                        + self.retry_backoff(state.retries).as_millis() as i64;
+                    state.retries = state.retries.saturating_add(1);
+                    let next = if retry_at < current.expire_ms {
+                        (retry_at, ScheduledAction::Refresh)
+                    } else {
+                        (current.expire_ms, ScheduledAction::Expire)
+                    };
+                    (Err(RefreshError::Refresh(e)), Some(next))
+                }
+            };
+            for waiter in waiters {
+                let _ = waiter.send(ret.clone());
+            }
+            (ret, next.map(|(at, action)| (state.generation, at, action)))
+        };
+        if let Some((generation, at, action)) = next {
+            self.schedule(generation, at, action);
+        }
+        ret
+    }
+
+    /// 第[retries]次重试前等待的时间
+    fn retry_backoff(&self, retries: u32) -> Duration {
+        let (initial, max) = self.inner.retry_backoff;
+        initial
+            .checked_mul(2u32.saturating_pow(retries))
+            .map_or(max, |backoff| backoff.min(max))
+    }
+
+    fn refresh_at(&self, auth: &AuthBody) -> i64 {
+        let ahead = (self.inner.refresh_ahead.as_millis() as i64).min(auth.expire_in_ms / 2);
+        auth.expire_ms - ahead
+    }
+
+    /// 开始一个新的会话阶段：取消定时任务，通知等待中的刷新请求
+    fn reset(&self, state: &mut State<R::Error>) {
+        state.generation += 1;
+        state.auth = None;
+        state.retries = 0;
+        self.cancel_scheduled(state);
+        for waiter in state.refreshing.take().unwrap_or_default() {
+            let _ = waiter.send(Err(RefreshError::Canceled));
+        }
+    }
+
+    /// 取消尚未执行的定时任务
+    fn cancel_scheduled(&self, state: &mut State<R::Error>) {
+        if let Some(id) = state.schedule_id.take()
+            && let Err(e) = self.inner.scheduler.clone().cancel(id)
+        {
+            warn!("取消token刷新任务失败: {e}");
+        }
+    }
+
+    fn notify(state: &mut State<R::Error>, event: SessionEvent<R::Error>) {
+        state
+            .listeners
+            .retain(|listener| listener.send(event.clone()).is_ok());
+    }
+
+    /// 安排定时任务，[Scheduler]返回任务id需要等待，因此在后台进行
+    fn schedule(&self, generation: u64, at_ms: i64, action: ScheduledAction) {
+        let delay = Duration::from_millis((at_ms - self.inner.clock.now_millis()).max(0) as u64);
+        let manager = self.clone();
+        async_runtime::spawn(async move {
+            let mut scheduler = manager.inner.scheduler.clone();
+            let on_time = manager.clone();
+            let ret = scheduler
+                .once_after(delay, move |_| {
+                    async_runtime::spawn(async move {
+                        on_time.on_scheduled(generation, action).await;
+                    });
+                })
+                .await;
+            match ret {
+                Ok(id) => {
+                    let mut state = manager.inner.state.lock().unwrap();
+                    if state.generation == generation {
+                        state.schedule_id = Some(id);
+                    } else if let Err(e) = scheduler.cancel(id) {
+                        warn!("取消token刷新任务失败: {e}");
+                    }
+                }
+                Err(e) => warn!("安排token刷新任务失败: {e}"),
+            }
+        });
+    }
+
+    async fn on_scheduled(&self, generation: u64, action: ScheduledAction) {
+        {
+            // 任务已经执行，不需要再取消
+            let mut state = self.inner.state.lock().unwrap();
+            if state.generation == generation {
+                state.schedule_id = None;
+            }
+        }
+        match action {
+            ScheduledAction::Refresh => {
+                if self.inner.state.lock().unwrap().generation == generation {
+                    let _ = self.refresh().await;
+                }
+            }
+            ScheduledAction::Expire => {
+                let mut state = self.inner.state.lock().unwrap();
+                if state.generation == generation && state.auth.is_some() {
+                    self.reset(&mut state);
+                    Self::notify(&mut state, SessionEvent::Ended(SessionEndReason::Expired));
+                }
+            }
+        }
+    }
+}
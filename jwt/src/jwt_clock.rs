@@ -0,0 +1,26 @@
+/// 时钟
+/// [JwtProvider](crate::jwt_provider::JwtProvider)与[TokenManager](crate::jwt_client::TokenManager)通过[JwtClock]获取当前时间，
+/// 测试时可以替换为可控的时钟，见`test_util::MockClock`
+pub trait JwtClock: Send + Sync {
+    /// 当前毫秒数
+    fn now_millis(&self) -> i64;
+}
+
+/// 系统时钟
+pub struct SystemClock;
+
+impl JwtClock for SystemClock {
+    #[cfg(feature = "server")]
+    fn now_millis(&self) -> i64 {
+        time::now_millis() as i64
+    }
+
+    /// 只启用client时(如wasm平台)使用跨平台的[types::SystemTime]
+    #[cfg(not(feature = "server"))]
+    fn now_millis(&self) -> i64 {
+        types::SystemTime::now()
+            .duration_since(types::UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,27 @@
+/// 代理授权的策略
+/// 见[JwtProvider::authorize_delegated](crate::jwt_provider::JwtProvider::authorize_delegated)
+///
+use crate::jwt_payload::Actor;
+
+/// 决定[Actor]是否可以代理指定的用户
+pub trait ImpersonationPolicy<PayloadType> {
+    fn allow(&self, actor: &Actor, subject: &PayloadType) -> bool;
+}
+
+impl<PayloadType, F> ImpersonationPolicy<PayloadType> for F
+where
+    F: Fn(&Actor, &PayloadType) -> bool,
+{
+    fn allow(&self, actor: &Actor, subject: &PayloadType) -> bool {
+        self(actor, subject)
+    }
+}
+
+/// 允许代理任何用户
+pub struct AllowAllImpersonation;
+
+impl<PayloadType> ImpersonationPolicy<PayloadType> for AllowAllImpersonation {
+    fn allow(&self, _actor: &Actor, _subject: &PayloadType) -> bool {
+        true
+    }
+}
@@ -1,23 +1,183 @@
-use chrono::{Duration, Local};
+use chrono::Local;
 use serde::Deserialize;
 use serde::Serialize;
 
+/// 授权后返回给客户端的数据
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct AuthBody {
+    pub token_id: String,
+    pub token: String,
+    // pub token_type: String,
+    pub expire_ms: i64,
+    pub expire_in_ms: i64,
+    /// 会话信息，用于列出用户的登录设备
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<SessionMetadata>,
+}
+
+/// 会话信息，授权时随[AuthBody]一起保存
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct SessionMetadata {
+    /// 会话所属的用户，存储以此列出用户的所有会话
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    /// 设备名称，如`iPhone 15`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// 授权时间(毫秒)
+    pub created_ms: i64,
+    /// 最后一次校验成功的时间(毫秒)
+    pub last_seen_ms: i64,
+}
+
+impl SessionMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    pub fn device(mut self, device: impl Into<String>) -> Self {
+        self.device = Some(device.into());
+        self
+    }
+
+    pub fn ip(mut self, ip: impl Into<String>) -> Self {
+        self.ip = Some(ip.into());
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+}
+
+/// 认证上下文，描述本次会话是如何完成认证的
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct AuthContext {
+    /// 认证等级，如`urn:example:mfa`
+    pub acr: Option<String>,
+    /// 认证方式，如`pwd`, `otp`
+    pub amr: Vec<String>,
+}
+
+impl AuthContext {
+    pub fn new(acr: Option<String>, amr: Vec<String>) -> Self {
+        AuthContext { acr, amr }
+    }
+}
+
+/// 代理授权中的真实操作者(RFC 8693 `act`)
+/// 如果操作者本身也是被代理的，通过内层的[Actor::act]形成代理链，最外层为最近的操作者
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Actor {
+    pub sub: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Box<Actor>>,
+}
+
+impl Actor {
+    pub fn new(sub: impl Into<String>) -> Self {
+        Actor {
+            sub: sub.into(),
+            act: None,
+        }
+    }
+
+    /// 操作者本身的会话也是代理授权时，保留之前的代理链
+    pub fn delegated_by(mut self, prior: Option<Actor>) -> Self {
+        self.act = prior.map(Box::new);
+        self
+    }
+
+    /// 从最近的操作者开始，遍历整个代理链
+    pub fn chain(&self) -> impl Iterator<Item = &Actor> {
+        std::iter::successors(Some(self), |actor| actor.act.as_deref())
+    }
+}
+
+impl std::fmt::Display for Actor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let chain: Vec<&str> = self.chain().map(|actor| actor.sub.as_str()).collect();
+        write!(f, "{}", chain.join(" <- "))
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub struct JwtPayload<PayLoadType> {
     pub token_id: String,
     pub payload: PayLoadType,
     pub expire_ms: i64,
+    /// 签发者，多租户时用于区分租户
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    /// 最近一次认证的时间(秒)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acr: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<String>,
+    /// 代理授权时的真实操作者，[JwtPayload::payload]为被代理的用户
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
 }
 
 impl<PayLoadType> JwtPayload<PayLoadType> {
     pub fn new(token_id: String, payload: PayLoadType, expire_in_ms: i64) -> Self {
-        let iat = Local::now();
-        let expire_ms = iat + Duration::milliseconds(expire_in_ms as i64);
-        let expire_ms = expire_ms.timestamp_millis() as i64;
+        Self::new_at(token_id, payload, Local::now().timestamp_millis(), expire_in_ms)
+    }
+
+    /// 以指定时间[now_ms]作为签发时间创建载荷
+    pub fn new_at(token_id: String, payload: PayLoadType, now_ms: i64, expire_in_ms: i64) -> Self {
+        let expire_ms = now_ms + expire_in_ms;
         JwtPayload {
             token_id,
             payload,
             expire_ms,
+            iss: None,
+            auth_time: Some(now_ms / 1000),
+            acr: None,
+            amr: Vec::new(),
+            act: None,
+        }
+    }
+
+    /// 代理授权时的真实操作者
+    pub fn actor(&self) -> Option<&Actor> {
+        self.act.as_ref()
+    }
+
+    /// 是否为代理授权
+    pub fn is_delegated(&self) -> bool {
+        self.act.is_some()
+    }
+
+    /// 认证上下文
+    pub fn auth_context(&self) -> AuthContext {
+        AuthContext {
+            acr: self.acr.clone(),
+            amr: self.amr.clone(),
         }
     }
+
+    /// 设置认证上下文，并将认证时间更新为当前时间
+    pub fn set_auth_context(&mut self, context: AuthContext) {
+        self.set_auth_context_at(context, Local::now().timestamp_millis());
+    }
+
+    /// 设置认证上下文，并将认证时间更新为[now_ms]
+    pub fn set_auth_context_at(&mut self, context: AuthContext, now_ms: i64) {
+        self.auth_time = Some(now_ms / 1000);
+        self.acr = context.acr;
+        self.amr = context.amr;
+    }
 }
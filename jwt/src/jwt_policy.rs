@@ -0,0 +1,588 @@
+/// 基于策略的授权(RBAC/ABAC)
+/// 角色及其继承关系，权限，以及资源策略由配置文件描述，见[PolicyConfig]
+/// [PolicyEngine]根据已校验的[JwtPayload]与请求属性进行判定，拒绝时给出可解释的原因，见[DenyReason]
+/// 处理函数可以使用[Authorized]提取器，或在加载资源后直接调用[PolicyEngine::evaluate]
+///
+/// 判定规则：
+/// 1. 任一匹配的`deny`策略拒绝请求
+/// 2. 否则，角色(含继承的角色)拥有对应权限，或任一`allow`策略匹配时允许
+/// 3. 否则拒绝
+///
+/// 条件中的属性以`.`分隔的路径引用，根节点为：
+/// - `subject`: 序列化后的[JwtPayload]，另外加上`roles`(展开继承后的所有角色)
+/// - `action`: 操作
+/// - `resource`: `name`为资源名，其余为[PolicyRequest::resource_attribute]设置的属性
+/// - `request`: [PolicyRequest::request_attribute]设置的属性，[Authorized]会自动设置`method`, `path`, `ip`
+///
+use crate::jwt_audit::ClientInfo;
+use crate::jwt_payload::JwtPayload;
+use crate::jwt_principal::PrincipalResolver;
+use axum_core::extract::FromRequestParts;
+use axum_core::response::{IntoResponse, Response};
+use http::StatusCode;
+use http::request::Parts;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+use std::path::Path;
+
+/// 策略配置
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct PolicyConfig {
+    /// 角色在[JwtPayload]中的位置，值可以是字符串或字符串数组，默认为`payload.roles`
+    #[serde(default = "default_roles_claim")]
+    pub roles_claim: String,
+    #[serde(default)]
+    pub roles: HashMap<String, RoleConfig>,
+    #[serde(default)]
+    pub policies: Vec<ResourcePolicy>,
+}
+
+fn default_roles_claim() -> String {
+    "payload.roles".to_string()
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RoleConfig {
+    /// 继承的角色，拥有被继承角色的所有权限
+    #[serde(default)]
+    pub inherits: Vec<String>,
+    /// 权限，格式为`{action}:{resource}`，两部分都支持`*`通配符，资源还支持`document/*`形式的前缀匹配
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    #[default]
+    Allow,
+    Deny,
+}
+
+/// 资源策略
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ResourcePolicy {
+    pub id: String,
+    #[serde(default)]
+    pub effect: Effect,
+    /// 资源，匹配规则同权限中的资源部分
+    pub resource: String,
+    /// 操作，为空时匹配所有操作
+    #[serde(default)]
+    pub actions: Vec<String>,
+    /// 只对拥有其中任一角色的调用者生效，为空时对所有调用者生效
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// 所有条件都满足时策略才生效
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+}
+
+/// 属性条件，如`{"attribute": "resource.owner", "op": "eq", "value": {"attribute": "subject.payload.id"}}`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Condition {
+    pub attribute: String,
+    pub op: ConditionOp,
+    #[serde(default)]
+    pub value: Operand,
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Operand::Attribute { attribute } => {
+                write!(f, "{} {} {attribute}", self.attribute, self.op)
+            }
+            Operand::Value(Value::Null) => write!(f, "{} {}", self.attribute, self.op),
+            Operand::Value(value) => write!(f, "{} {} {value}", self.attribute, self.op),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConditionOp {
+    /// 属性存在且相等
+    Eq,
+    /// 两边的属性都存在且不相等，属性缺失时不成立
+    Ne,
+    /// 属性值在数组中
+    In,
+    /// 属性值(数组或字符串)包含给定值
+    Contains,
+    /// 属性存在且不为null，忽略value
+    Exists,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl Display for ConditionOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            ConditionOp::Eq => "==",
+            ConditionOp::Ne => "!=",
+            ConditionOp::In => "in",
+            ConditionOp::Contains => "contains",
+            ConditionOp::Exists => "exists",
+            ConditionOp::Gt => ">",
+            ConditionOp::Gte => ">=",
+            ConditionOp::Lt => "<",
+            ConditionOp::Lte => "<=",
+        };
+        f.write_str(op)
+    }
+}
+
+/// 条件中比较的值，可以是常量，也可以引用另一个属性
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Operand {
+    Attribute { attribute: String },
+    Value(Value),
+}
+
+impl Default for Operand {
+    fn default() -> Self {
+        Operand::Value(Value::Null)
+    }
+}
+
+#[derive(Debug)]
+pub enum PolicyError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    /// 继承了未定义的角色
+    UnknownRole { role: String, inherits: String },
+    /// 角色继承存在环
+    InheritanceCycle(String),
+}
+
+impl Display for PolicyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolicyError::Io(e) => write!(f, "failed to read policy config: {e}"),
+            PolicyError::Parse(e) => write!(f, "failed to parse policy config: {e}"),
+            PolicyError::UnknownRole { role, inherits } => {
+                write!(f, "role {role} inherits unknown role {inherits}")
+            }
+            PolicyError::InheritanceCycle(role) => {
+                write!(f, "role inheritance cycle at {role}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+/// 一次授权判定的请求
+#[derive(Clone, Debug)]
+pub struct PolicyRequest {
+    pub action: String,
+    pub resource: String,
+    pub resource_attributes: Map<String, Value>,
+    pub request_attributes: Map<String, Value>,
+}
+
+impl PolicyRequest {
+    pub fn new(action: impl Into<String>, resource: impl Into<String>) -> Self {
+        PolicyRequest {
+            action: action.into(),
+            resource: resource.into(),
+            resource_attributes: Map::new(),
+            request_attributes: Map::new(),
+        }
+    }
+
+    /// 设置资源属性，条件中以`resource.{key}`引用
+    pub fn resource_attribute(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.resource_attributes.insert(key.into(), value.into());
+        self
+    }
+
+    /// 设置请求属性，条件中以`request.{key}`引用
+    pub fn request_attribute(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.request_attributes.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// 拒绝的原因
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum DenyReason {
+    /// 被`deny`策略拒绝
+    DeniedByPolicy { policy: String },
+    /// 没有角色拥有对应权限
+    NoPermission {
+        action: String,
+        resource: String,
+        roles: Vec<String>,
+    },
+    /// `allow`策略要求的角色不满足
+    MissingRole { policy: String, roles: Vec<String> },
+    /// `allow`策略的条件不满足
+    ConditionFailed { policy: String, condition: String },
+}
+
+impl Display for DenyReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DenyReason::DeniedByPolicy { policy } => write!(f, "denied by policy {policy}"),
+            DenyReason::NoPermission {
+                action,
+                resource,
+                roles,
+            } => write!(
+                f,
+                "roles [{}] have no permission {action}:{resource}",
+                roles.join(", ")
+            ),
+            DenyReason::MissingRole { policy, roles } => {
+                write!(f, "policy {policy} requires one of roles [{}]", roles.join(", "))
+            }
+            DenyReason::ConditionFailed { policy, condition } => {
+                write!(f, "policy {policy} condition not met: {condition}")
+            }
+        }
+    }
+}
+
+/// 判定结果
+#[derive(Clone, Debug, PartialEq)]
+pub enum Decision {
+    /// 允许，[granted_by]为授予权限的角色或策略
+    Allow { granted_by: String },
+    Deny(Vec<DenyReason>),
+}
+
+impl Decision {
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, Decision::Allow { .. })
+    }
+
+    pub fn into_result(self) -> Result<(), PolicyDenied> {
+        match self {
+            Decision::Allow { .. } => Ok(()),
+            Decision::Deny(reasons) => Err(PolicyDenied { reasons }),
+        }
+    }
+}
+
+/// 授权被拒绝，响应为403，并给出所有拒绝原因
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolicyDenied {
+    pub reasons: Vec<DenyReason>,
+}
+
+impl IntoResponse for PolicyDenied {
+    fn into_response(self) -> Response {
+        let body = axum::Json(serde_json::json!({
+            "error": "forbidden",
+            "reasons": self.reasons.iter().map(|reason| reason.to_string()).collect::<Vec<_>>(),
+            "details": self.reasons,
+        }));
+        (StatusCode::FORBIDDEN, body).into_response()
+    }
+}
+
+pub struct PolicyEngine {
+    config: PolicyConfig,
+    /// 角色 -> 自身及所有继承的角色
+    expanded_roles: HashMap<String, HashSet<String>>,
+}
+
+impl PolicyEngine {
+    /// 检查配置并创建，继承未定义的角色或继承关系存在环时返回错误
+    pub fn new(config: PolicyConfig) -> Result<Self, PolicyError> {
+        let mut expanded_roles = HashMap::new();
+        for role in config.roles.keys() {
+            let mut expanded = HashSet::new();
+            expand_role(&config, role, &mut Vec::new(), &mut expanded)?;
+            expanded_roles.insert(role.clone(), expanded);
+        }
+        Ok(PolicyEngine {
+            config,
+            expanded_roles,
+        })
+    }
+
+    /// 从json格式的配置创建
+    pub fn from_json(json: &str) -> Result<Self, PolicyError> {
+        Self::new(serde_json::from_str(json).map_err(PolicyError::Parse)?)
+    }
+
+    /// 从json格式的配置文件创建
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, PolicyError> {
+        let json = std::fs::read_to_string(path).map_err(PolicyError::Io)?;
+        Self::from_json(&json)
+    }
+
+    pub fn config(&self) -> &PolicyConfig {
+        &self.config
+    }
+
+    /// 展开继承后的角色，未定义的角色原样保留
+    pub fn expand_roles(&self, roles: &[String]) -> Vec<String> {
+        let mut expanded: Vec<String> = roles
+            .iter()
+            .flat_map(|role| match self.expanded_roles.get(role) {
+                Some(expanded) => expanded.iter().cloned().collect(),
+                None => vec![role.clone()],
+            })
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        expanded.sort();
+        expanded
+    }
+
+    /// 对[subject]的请求进行判定
+    pub fn evaluate<PayloadType: Serialize>(
+        &self,
+        subject: &JwtPayload<PayloadType>,
+        request: &PolicyRequest,
+    ) -> Decision {
+        let mut subject = serde_json::to_value(subject).unwrap_or(Value::Null);
+        let roles = self.expand_roles(&roles_of(lookup(&subject, &self.config.roles_claim)));
+        if let Value::Object(subject) = &mut subject {
+            subject.insert("roles".to_string(), roles.clone().into());
+        }
+        let mut resource = request.resource_attributes.clone();
+        resource.insert("name".to_string(), request.resource.clone().into());
+        let context = serde_json::json!({
+            "subject": subject,
+            "action": request.action,
+            "resource": resource,
+            "request": request.request_attributes,
+        });
+
+        let mut granted_by = roles.iter().find_map(|role| {
+            self.config.roles.get(role).and_then(|config| {
+                config
+                    .permissions
+                    .iter()
+                    .any(|permission| permission_matches(permission, &request.action, &request.resource))
+                    .then(|| format!("role {role}"))
+            })
+        });
+        let mut reasons = Vec::new();
+        let mut denied = Vec::new();
+        for policy in &self.config.policies {
+            if !resource_matches(&policy.resource, &request.resource)
+                || !(policy.actions.is_empty()
+                    || policy.actions.iter().any(|action| pattern_matches(action, &request.action)))
+            {
+                continue;
+            }
+            let reason = if !policy.roles.is_empty()
+                && !policy.roles.iter().any(|role| roles.contains(role))
+            {
+                Some(DenyReason::MissingRole {
+                    policy: policy.id.clone(),
+                    roles: policy.roles.clone(),
+                })
+            } else {
+                policy
+                    .conditions
+                    .iter()
+                    .find(|condition| !condition_holds(condition, &context))
+                    .map(|condition| DenyReason::ConditionFailed {
+                        policy: policy.id.clone(),
+                        condition: condition.to_string(),
+                    })
+            };
+            match (policy.effect, reason) {
+                (Effect::Deny, None) => denied.push(DenyReason::DeniedByPolicy {
+                    policy: policy.id.clone(),
+                }),
+                (Effect::Allow, None) => {
+                    granted_by.get_or_insert_with(|| format!("policy {}", policy.id));
+                }
+                (Effect::Allow, Some(reason)) => reasons.push(reason),
+                (Effect::Deny, Some(_)) => {}
+            }
+        }
+        if !denied.is_empty() {
+            return Decision::Deny(denied);
+        }
+        match granted_by {
+            Some(granted_by) => Decision::Allow { granted_by },
+            None => {
+                reasons.insert(
+                    0,
+                    DenyReason::NoPermission {
+                        action: request.action.clone(),
+                        resource: request.resource.clone(),
+                        roles,
+                    },
+                );
+                Decision::Deny(reasons)
+            }
+        }
+    }
+}
+
+fn expand_role(
+    config: &PolicyConfig,
+    role: &str,
+    path: &mut Vec<String>,
+    expanded: &mut HashSet<String>,
+) -> Result<(), PolicyError> {
+    if path.iter().any(|r| r == role) {
+        return Err(PolicyError::InheritanceCycle(role.to_string()));
+    }
+    if !expanded.insert(role.to_string()) {
+        return Ok(());
+    }
+    path.push(role.to_string());
+    for inherits in &config.roles[role].inherits {
+        if !config.roles.contains_key(inherits) {
+            return Err(PolicyError::UnknownRole {
+                role: role.to_string(),
+                inherits: inherits.clone(),
+            });
+        }
+        expand_role(config, inherits, path, expanded)?;
+    }
+    path.pop();
+    Ok(())
+}
+
+fn roles_of(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(role)) => vec![role.clone()],
+        Some(Value::Array(roles)) => roles
+            .iter()
+            .filter_map(|role| role.as_str().map(|role| role.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| match value {
+        Value::Object(map) => map.get(key),
+        Value::Array(values) => key.parse::<usize>().ok().and_then(|i| values.get(i)),
+        _ => None,
+    })
+}
+
+/// `*`匹配所有，以`*`结尾时按前缀匹配
+fn pattern_matches(pattern: &str, value: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => value.starts_with(prefix),
+        None => pattern == value,
+    }
+}
+
+fn resource_matches(pattern: &str, resource: &str) -> bool {
+    pattern_matches(pattern, resource)
+}
+
+fn permission_matches(permission: &str, action: &str, resource: &str) -> bool {
+    match permission.split_once(':') {
+        Some((action_pattern, resource_pattern)) => {
+            pattern_matches(action_pattern, action) && resource_matches(resource_pattern, resource)
+        }
+        None => permission == "*",
+    }
+}
+
+fn condition_holds(condition: &Condition, context: &Value) -> bool {
+    let left = lookup(context, &condition.attribute);
+    let right = match &condition.value {
+        Operand::Attribute { attribute } => lookup(context, attribute),
+        Operand::Value(value) => Some(value),
+    };
+    match condition.op {
+        ConditionOp::Exists => left.is_some_and(|left| !left.is_null()),
+        ConditionOp::Eq => left.is_some() && left == right,
+        ConditionOp::Ne => left.is_some() && right.is_some() && left != right,
+        ConditionOp::In => match (left, right) {
+            (Some(left), Some(Value::Array(values))) => values.contains(left),
+            _ => false,
+        },
+        ConditionOp::Contains => match (left, right) {
+            (Some(Value::Array(values)), Some(right)) => values.contains(right),
+            (Some(Value::String(left)), Some(Value::String(right))) => left.contains(right.as_str()),
+            _ => false,
+        },
+        ConditionOp::Gt | ConditionOp::Gte | ConditionOp::Lt | ConditionOp::Lte => {
+            let (Some(left), Some(right)) = (
+                left.and_then(Value::as_f64),
+                right.and_then(Value::as_f64),
+            ) else {
+                return false;
+            };
+            match condition.op {
+                ConditionOp::Gt => left > right,
+                ConditionOp::Gte => left >= right,
+                ConditionOp::Lt => left < right,
+                _ => left <= right,
+            }
+        }
+    }
+}
+
+/// 为[Authorized]提供策略引擎，应用的状态需要同时实现[PrincipalResolver]
+pub trait PolicyResolver<PayloadType>: PrincipalResolver<PayloadType> {
+    fn policy_engine(&self) -> &PolicyEngine;
+}
+
+/// 描述受保护的操作，一般为每个路由定义一个类型
+pub trait AccessRule {
+    /// 根据请求生成判定请求，如从路径中取出资源id
+    fn request(parts: &Parts) -> PolicyRequest;
+}
+
+pub enum AuthorizationRejection<Rejection> {
+    /// 未通过身份校验
+    Unauthorized(Rejection),
+    Forbidden(PolicyDenied),
+}
+
+impl<Rejection: IntoResponse> IntoResponse for AuthorizationRejection<Rejection> {
+    fn into_response(self) -> Response {
+        match self {
+            AuthorizationRejection::Unauthorized(e) => e.into_response(),
+            AuthorizationRejection::Forbidden(e) => e.into_response(),
+        }
+    }
+}
+
+/// 通过[AccessRule]授权判定的调用者，用法与[crate::jwt_principal::Principal]相同
+pub struct Authorized<PayloadType, Rule>(pub JwtPayload<PayloadType>, PhantomData<Rule>);
+
+impl<State, PayloadType, Rule> FromRequestParts<State> for Authorized<PayloadType, Rule>
+where
+    State: PolicyResolver<PayloadType>,
+    PayloadType: Serialize,
+    Rule: AccessRule,
+{
+    type Rejection = AuthorizationRejection<State::Rejection>;
+
+    async fn from_request_parts(parts: &mut Parts, state: &State) -> Result<Self, Self::Rejection> {
+        let payload = state
+            .resolve(parts)
+            .await
+            .map_err(AuthorizationRejection::Unauthorized)?;
+        let mut request = Rule::request(parts)
+            .request_attribute("method", parts.method.as_str())
+            .request_attribute("path", parts.uri.path());
+        // 与审计日志一致，ip由[http_utils::client_ip::ClientIp]解析，只信任配置的代理
+        if let Some(ip) = ClientInfo::from_parts(parts).ip {
+            request = request.request_attribute("ip", ip);
+        }
+        state
+            .policy_engine()
+            .evaluate(&payload, &request)
+            .into_result()
+            .map_err(AuthorizationRejection::Forbidden)?;
+        Ok(Authorized(payload, PhantomData))
+    }
+}
@@ -0,0 +1,101 @@
+/// 已通过校验的调用者
+/// jwt与API key校验成功后都返回[JwtPayload]，处理函数使用[Principal]提取器即可同时接受两种授权方式
+///
+/// 由于提取器需要访问授权提供者，应用的状态需要实现[PrincipalResolver]，
+/// 一般在其中调用[resolve_bearer_or_api_key]即可
+///
+use crate::api_key_provider::{ApiKeyError, ApiKeyProvider};
+use crate::api_key_storage_provider::ApiKeyStorageProvider;
+use crate::jwt_auth_provider::JwtAsyncAuthProvider;
+use crate::jwt_bear_provider::{BearAuthError, JwtBearerProvider};
+use crate::jwt_clock::{JwtClock, SystemClock};
+use crate::jwt_payload::JwtPayload;
+use crate::jwt_storage_provider::JwtStorageProvider;
+use axum_core::extract::FromRequestParts;
+use axum_core::response::{IntoResponse, Response};
+use http::request::Parts;
+
+/// 已通过校验的调用者，可以来自jwt或API key
+pub struct Principal<PayloadType>(pub JwtPayload<PayloadType>);
+
+/// 从请求中解析调用者
+pub trait PrincipalResolver<PayloadType>: Send + Sync {
+    type Rejection: IntoResponse;
+
+    fn resolve(
+        &self,
+        parts: &mut Parts,
+    ) -> impl Future<Output = Result<JwtPayload<PayloadType>, Self::Rejection>> + Send;
+
+    /// 校验使用的时钟，[StepUp](crate::jwt_step_up::StepUp)用它判断认证时间，
+    /// 一般返回[JwtProvider::clock](crate::jwt_provider::JwtProvider::clock)，默认使用[SystemClock]
+    fn clock(&self) -> &dyn JwtClock {
+        &SystemClock
+    }
+}
+
+impl<State, PayloadType> FromRequestParts<State> for Principal<PayloadType>
+where
+    State: PrincipalResolver<PayloadType>,
+{
+    type Rejection = State::Rejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &State) -> Result<Self, Self::Rejection> {
+        state.resolve(parts).await.map(Principal)
+    }
+}
+
+pub enum PrincipalError<BearerError, ApiKeyErrorType> {
+    Bearer(BearerError),
+    ApiKey(ApiKeyErrorType),
+}
+
+impl<BearerError: IntoResponse, ApiKeyErrorType: IntoResponse> IntoResponse
+    for PrincipalError<BearerError, ApiKeyErrorType>
+{
+    fn into_response(self) -> Response {
+        match self {
+            PrincipalError::Bearer(e) => e.into_response(),
+            PrincipalError::ApiKey(e) => e.into_response(),
+        }
+    }
+}
+
+/// 请求头中带有API key时使用API key校验，否则使用bear token校验
+/// [required_scopes]只对API key生效
+#[allow(clippy::type_complexity)]
+pub async fn resolve_bearer_or_api_key<
+    PayloadType,
+    JwtAuthProviderType,
+    JwtStorageProviderType,
+    ApiKeyStorageProviderType,
+>(
+    parts: &mut Parts,
+    bearer: &JwtBearerProvider<JwtAuthProviderType, JwtStorageProviderType>,
+    api_keys: &ApiKeyProvider<ApiKeyStorageProviderType>,
+    required_scopes: &[&str],
+) -> Result<
+    JwtPayload<PayloadType>,
+    PrincipalError<
+        BearAuthError<
+            <JwtStorageProviderType as JwtStorageProvider>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<PayloadType>>>::Error,
+        >,
+        ApiKeyError<<ApiKeyStorageProviderType as ApiKeyStorageProvider<PayloadType>>::Error>,
+    >,
+>
+where
+    PayloadType: serde::Serialize + std::cmp::PartialEq,
+    JwtAuthProviderType: JwtAsyncAuthProvider<JwtPayload<PayloadType>>,
+    JwtStorageProviderType: JwtStorageProvider,
+    ApiKeyStorageProviderType: ApiKeyStorageProvider<PayloadType>,
+{
+    if parts.headers.contains_key(api_keys.header()) {
+        api_keys
+            .verify_parts(parts, required_scopes)
+            .await
+            .map_err(PrincipalError::ApiKey)
+    } else {
+        bearer.verify(parts).await.map_err(PrincipalError::Bearer)
+    }
+}
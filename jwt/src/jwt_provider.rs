@@ -1,23 +1,25 @@
 /// 用于用户授权的工具模块
 /// 封装了token的生成，加密，存储与校验流程
-/// 用户可以自定义加密与解码方式，见[super::jwt_auth_provider::JwtAuthProvider]
+/// 用户可以自定义加密与解码方式，见[super::jwt_auth_provider::JwtAuthProvider]，
+/// [JwtProvider]使用异步的授权器，自定义的同步授权器可以直接使用，载荷与错误类型需要是`Send`的
+/// 私钥保存在外部服务时见[super::jwt_auth_provider::JwtAsyncAuthProvider]
 /// 当前提供了一些默认的加密器，见[super::jwt_auth_provider::HmacAuthProvider]
 /// 用户也可以自定义生成的token的存储方式，见[super::jwt_storage_provider::JwtStorageProvider]
+/// 授权过程中的事件会通知给[super::jwt_audit::JwtAuditObserver]，默认输出`tracing`日志
 ///
 ///
-use crate::jwt_auth_provider::JwtAuthProvider;
-use crate::jwt_payload::JwtPayload;
+use crate::jwt_audit::{ClientInfo, JwtAuditObserver, TracingAuditObserver};
+use crate::jwt_auth_provider::JwtAsyncAuthProvider;
+use crate::jwt_clock::{JwtClock, SystemClock};
+use crate::jwt_delegation::ImpersonationPolicy;
+use crate::jwt_payload::{Actor, AuthContext, JwtPayload, SessionMetadata};
+use crate::jwt_revocation::{RevocationFormat, RevocationList, RevocationRegistry};
 use crate::jwt_storage_provider::JwtStorageProvider;
-use serde::{Deserialize, Serialize};
-
-#[derive(Clone, Serialize, Deserialize, Debug)]
-pub struct AuthBody {
-    pub token_id: String,
-    pub token: String,
-    // pub token_type: String,
-    pub expire_ms: i64,
-    pub expire_in_ms: i64,
-}
+use crate::jwt_token_id::{Scru128TokenIdGenerator, TokenIdGenerator};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub use crate::jwt_payload::AuthBody;
 
 #[derive(Debug)]
 pub enum AuthError<StorageError, DecodeError> {
@@ -26,6 +28,36 @@ pub enum AuthError<StorageError, DecodeError> {
     OutOfDate,
     NoAuthDataFound,
     AuthDataNotMatch,
+    /// 代理授权被[ImpersonationPolicy]拒绝
+    ImpersonationForbidden,
+}
+
+/// [AuthError]的种类，不携带具体错误数据，用于审计等场景
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthErrorKind {
+    StorageError,
+    DecodeError,
+    OutOfDate,
+    NoAuthDataFound,
+    AuthDataNotMatch,
+    ImpersonationForbidden,
+    /// 请求中没有合法的bear token, 只会由[super::jwt_bear_provider::JwtBearerProvider]产生
+    InvalidBearer,
+    /// 失败次数过多，被[super::jwt_rate_limiter::JwtRateLimiter]拒绝
+    RateLimited,
+}
+
+impl<StorageError, DecodeError> AuthError<StorageError, DecodeError> {
+    pub fn kind(&self) -> AuthErrorKind {
+        match self {
+            AuthError::StorageError(_) => AuthErrorKind::StorageError,
+            AuthError::DecodeError(_) => AuthErrorKind::DecodeError,
+            AuthError::OutOfDate => AuthErrorKind::OutOfDate,
+            AuthError::NoAuthDataFound => AuthErrorKind::NoAuthDataFound,
+            AuthError::AuthDataNotMatch => AuthErrorKind::AuthDataNotMatch,
+            AuthError::ImpersonationForbidden => AuthErrorKind::ImpersonationForbidden,
+        }
+    }
 }
 
 pub struct JwtProvider<JwtAuthProviderType, JwtStorageProviderType> {
@@ -34,14 +66,21 @@ pub struct JwtProvider<JwtAuthProviderType, JwtStorageProviderType> {
     // config: JwtAuthConfig,
     auth_provider: JwtAuthProviderType,
     storage_provider: JwtStorageProviderType,
+    observer: Arc<dyn JwtAuditObserver>,
+    issuer: Option<String>,
+    clock: Arc<dyn JwtClock>,
+    token_id_generator: Arc<dyn TokenIdGenerator>,
+    last_seen_interval: Duration,
+    revocations: Option<RevocationRegistry>,
 }
 
 impl<JwtAuthProviderType, JwtStorageProviderType>
     JwtProvider<JwtAuthProviderType, JwtStorageProviderType>
 where
     JwtStorageProviderType: super::jwt_storage_provider::JwtStorageProvider,
-    // JwtAuthProviderType: super::jwt_auth_provider::JwtAuthProvider<JwtPayload<JwtPayloadType>>,
+    // JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
 {
+    /// [coder]需要实现[JwtAsyncAuthProvider]，[JwtAuthProvider](crate::jwt_auth_provider::JwtAuthProvider)的实现都已自动实现
     pub fn new(
         expire_in_ms: i64,
         coder: JwtAuthProviderType,
@@ -52,9 +91,65 @@ where
             expire_in_ms,
             auth_provider: coder,
             storage_provider: saver,
+            observer: Arc::new(TracingAuditObserver),
+            issuer: None,
+            clock: Arc::new(SystemClock),
+            token_id_generator: Arc::new(Scru128TokenIdGenerator),
+            last_seen_interval: Duration::from_secs(60),
+            revocations: None,
         }
     }
 
+    /// 设置时钟，默认使用[SystemClock]
+    pub fn with_clock(mut self, clock: impl JwtClock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// 设置token_id的生成方式，默认使用[Scru128TokenIdGenerator]，也可以传入`Fn() -> String`
+    pub fn with_token_id_generator(
+        mut self,
+        token_id_generator: impl TokenIdGenerator + 'static,
+    ) -> Self {
+        self.token_id_generator = Arc::new(token_id_generator);
+        self
+    }
+
+    /// 设置更新会话最后活跃时间的最小间隔，默认60秒
+    /// 校验成功时，距离上次更新超过该间隔才会写入存储
+    pub fn with_last_seen_interval(mut self, interval: Duration) -> Self {
+        self.last_seen_interval = interval;
+        self
+    }
+
+    /// 设置吊销记录，设置后删除授权时会记录被吊销的token_id，并可以发布吊销列表
+    pub fn with_revocation_registry(mut self, registry: RevocationRegistry) -> Self {
+        self.revocations = Some(registry);
+        self
+    }
+
+    /// 吊销记录
+    pub fn revocation_registry(&self) -> Option<&RevocationRegistry> {
+        self.revocations.as_ref()
+    }
+
+    /// 设置签发者，设置后签发的token会带上`iss`，校验时也会检查`iss`是否一致
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    /// 签发者
+    pub fn issuer(&self) -> Option<&str> {
+        self.issuer.as_deref()
+    }
+
+    /// 设置审计观察者，替换默认的[TracingAuditObserver]
+    pub fn with_observer(mut self, observer: impl JwtAuditObserver + 'static) -> Self {
+        self.observer = Arc::new(observer);
+        self
+    }
+
     /// 授权并返回token
     pub async fn authorize<JwtPayloadType>(
         &self,
@@ -63,23 +158,165 @@ where
         AuthBody,
         AuthError<
             <JwtStorageProviderType as JwtStorageProvider>::Error,
-            <JwtAuthProviderType as JwtAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+        >,
+    >
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        self.authorize_with_client(payload, &ClientInfo::default()).await
+    }
+
+    /// 授权并返回token, 同时将客户端信息提供给审计观察者
+    pub async fn authorize_with_client<JwtPayloadType>(
+        &self,
+        payload: JwtPayloadType,
+        client: &ClientInfo,
+    ) -> Result<
+        AuthBody,
+        AuthError<
+            <JwtStorageProviderType as JwtStorageProvider>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+        >,
+    >
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        self.authorize_with_context(payload, AuthContext::default(), client)
+            .await
+    }
+
+    /// 授权并返回token，token中会记录认证上下文(acr, amr)与认证时间
+    pub async fn authorize_with_context<JwtPayloadType>(
+        &self,
+        payload: JwtPayloadType,
+        context: AuthContext,
+        client: &ClientInfo,
+    ) -> Result<
+        AuthBody,
+        AuthError<
+            <JwtStorageProviderType as JwtStorageProvider>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+        >,
+    >
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        self.authorize_with_session(payload, context, SessionMetadata::default(), client)
+            .await
+    }
+
+    /// 授权并返回token，同时保存会话信息
+    /// [session]中未设置的ip, user_agent会从[client]中获取，授权时间与最后活跃时间为当前时间
+    pub async fn authorize_with_session<JwtPayloadType>(
+        &self,
+        payload: JwtPayloadType,
+        context: AuthContext,
+        session: SessionMetadata,
+        client: &ClientInfo,
+    ) -> Result<
+        AuthBody,
+        AuthError<
+            <JwtStorageProviderType as JwtStorageProvider>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+        >,
+    >
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        let payload = self.new_payload(payload, context);
+        self.issue(payload, session, client).await
+    }
+
+    /// 代理授权(如客服以用户身份操作)，签发的token中以`act`记录真实的操作者
+    /// [actor]为操作者，如果操作者本身的会话也是代理授权，应通过[Actor::delegated_by]保留完整的代理链
+    /// [policy]用于禁止代理某些账号(如管理员)，被拒绝时返回[AuthError::ImpersonationForbidden]
+    pub async fn authorize_delegated<JwtPayloadType>(
+        &self,
+        payload: JwtPayloadType,
+        actor: Actor,
+        policy: &impl ImpersonationPolicy<JwtPayloadType>,
+        client: &ClientInfo,
+    ) -> Result<
+        AuthBody,
+        AuthError<
+            <JwtStorageProviderType as JwtStorageProvider>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
         >,
     >
     where
         JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
-        JwtAuthProviderType: super::jwt_auth_provider::JwtAuthProvider<JwtPayload<JwtPayloadType>>,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
     {
-        let token_id = scru128::new_string();
-        let payload = JwtPayload::new(token_id, payload, self.expire_in_ms);
-        let body = self.gen_auth_body(payload);
-        match body {
+        if !policy.allow(&actor, &payload) {
+            let e = AuthError::ImpersonationForbidden;
+            self.observer.on_authorize_failure(e.kind(), client);
+            return Err(e);
+        }
+        let mut payload = self.new_payload(payload, AuthContext::default());
+        payload.act = Some(actor.clone());
+        let body = self
+            .issue(payload, SessionMetadata::default(), client)
+            .await?;
+        self.observer.on_delegate(&body.token_id, &actor, client);
+        Ok(body)
+    }
+
+    fn new_payload<JwtPayloadType>(
+        &self,
+        payload: JwtPayloadType,
+        context: AuthContext,
+    ) -> JwtPayload<JwtPayloadType> {
+        let token_id = self.token_id_generator.generate();
+        let now = self.clock.now_millis();
+        let mut payload = JwtPayload::new_at(token_id, payload, now, self.expire_in_ms);
+        payload.iss = self.issuer.clone();
+        payload.set_auth_context_at(context, now);
+        payload
+    }
+
+    /// 签发token并与会话信息一起保存
+    async fn issue<JwtPayloadType>(
+        &self,
+        payload: JwtPayload<JwtPayloadType>,
+        mut session: SessionMetadata,
+        client: &ClientInfo,
+    ) -> Result<
+        AuthBody,
+        AuthError<
+            <JwtStorageProviderType as JwtStorageProvider>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+        >,
+    >
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        let now = self.clock.now_millis();
+        session.ip = session.ip.or_else(|| client.ip.clone());
+        session.user_agent = session.user_agent.or_else(|| client.user_agent.clone());
+        session.created_ms = now;
+        session.last_seen_ms = now;
+        let body = self.gen_auth_body(payload).await.map(|mut body| {
+            body.session = Some(session);
+            body
+        });
+        let ret = match body {
             Ok(body) => match self.save(body.clone()).await {
                 Ok(_) => Ok(body),
                 Err(e) => Err(AuthError::StorageError(e)),
             },
             Err(e) => Err(AuthError::DecodeError(e)),
+        };
+        match &ret {
+            Ok(body) => self.observer.on_authorize(&body.token_id, client),
+            Err(e) => self.observer.on_authorize_failure(e.kind(), client),
         }
+        ret
     }
 
     /// 检查指定token是否有效
@@ -91,41 +328,183 @@ where
         JwtPayload<JwtPayloadType>,
         AuthError<
             <JwtStorageProviderType as JwtStorageProvider>::Error,
-            <JwtAuthProviderType as JwtAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
         >,
     >
     where
         JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
-        JwtAuthProviderType: super::jwt_auth_provider::JwtAuthProvider<JwtPayload<JwtPayloadType>>,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        self.verify_with_client(token, &ClientInfo::default()).await
+    }
+
+    /// 同[Self::verify]，同时将客户端信息提供给审计观察者
+    pub async fn verify_with_client<JwtPayloadType>(
+        &self,
+        token: &str,
+        client: &ClientInfo,
+    ) -> Result<
+        JwtPayload<JwtPayloadType>,
+        AuthError<
+            <JwtStorageProviderType as JwtStorageProvider>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+        >,
+    >
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        let (token_id, ret) = self.do_verify::<JwtPayloadType>(token).await;
+        match &ret {
+            Ok(payload) => {
+                self.observer
+                    .on_verify_success(&payload.token_id, payload.act.as_ref(), client)
+            }
+            Err(e) => self
+                .observer
+                .on_verify_failure(token_id.as_deref(), e.kind(), client),
+        }
+        ret
+    }
+
+    /// 执行校验，同时返回解码得到的token_id(如果能够解码)
+    #[allow(clippy::type_complexity)]
+    async fn do_verify<JwtPayloadType>(
+        &self,
+        token: &str,
+    ) -> (
+        Option<String>,
+        Result<
+            JwtPayload<JwtPayloadType>,
+            AuthError<
+                <JwtStorageProviderType as JwtStorageProvider>::Error,
+                <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+            >,
+        >,
+    )
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
     {
-        // let auth = self.saver.load(token_id).await?;
-        match self.auth_provider.decode(token) {
-            Ok(payload) => match self.storage_provider.load(&payload.token_id).await {
-                Ok(saved) => {
-                    if let Some(saved) = saved {
-                        let now = time::now_millis() as i64;
-                        if now < saved.expire_ms {
-                            match self.auth_provider.decode(&saved.token) {
-                                Ok(saved_payload) => {
-                                    if saved_payload.payload == payload.payload {
-                                        Ok(saved_payload)
-                                    } else {
-                                        Err(AuthError::AuthDataNotMatch)
-                                    }
+        let payload = match self.auth_provider.decode(token).await {
+            Ok(payload) => payload,
+            Err(e) => return (None, Err(AuthError::DecodeError(e))),
+        };
+        let token_id = Some(payload.token_id.clone());
+        if self.issuer.is_some() && payload.iss != self.issuer {
+            return (token_id, Err(AuthError::AuthDataNotMatch));
+        }
+        let ret = match self.storage_provider.load(&payload.token_id).await {
+            Ok(saved) => {
+                if let Some(saved) = saved {
+                    let now = self.clock.now_millis();
+                    if now < saved.expire_ms {
+                        match self.auth_provider.decode(&saved.token).await {
+                            Ok(saved_payload) => {
+                                if saved_payload.payload == payload.payload {
+                                    self.touch(&saved, now).await;
+                                    Ok(saved_payload)
+                                } else {
+                                    Err(AuthError::AuthDataNotMatch)
                                 }
-                                Err(e) => Err(AuthError::DecodeError(e)),
                             }
-                        } else {
-                            Err(AuthError::OutOfDate)
+                            Err(e) => Err(AuthError::DecodeError(e)),
                         }
                     } else {
-                        Err(AuthError::NoAuthDataFound)
+                        Err(AuthError::OutOfDate)
                     }
+                } else {
+                    Err(AuthError::NoAuthDataFound)
                 }
-                Err(e) => Err(AuthError::StorageError(e)),
-            },
-            Err(e) => Err(AuthError::DecodeError(e)),
-        }
+            }
+            Err(e) => Err(AuthError::StorageError(e)),
+        };
+        (token_id, ret)
+    }
+
+    /// 续期：校验token后签发新的token，并删除旧的授权
+    /// 新token保留原有的认证上下文，认证时间，代理信息与会话信息，过期时间重新计算
+    pub async fn refresh_with_client<JwtPayloadType>(
+        &self,
+        token: &str,
+        client: &ClientInfo,
+    ) -> Result<
+        AuthBody,
+        AuthError<
+            <JwtStorageProviderType as JwtStorageProvider>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+        >,
+    >
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        let current = self
+            .verify_with_client::<JwtPayloadType>(token, client)
+            .await?;
+        let session = self
+            .storage_provider
+            .load(&current.token_id)
+            .await
+            .map_err(AuthError::StorageError)?
+            .and_then(|saved| saved.session)
+            .unwrap_or_default();
+        let mut payload = self.new_payload(current.payload, AuthContext::default());
+        payload.auth_time = current.auth_time;
+        payload.acr = current.acr;
+        payload.amr = current.amr;
+        payload.act = current.act;
+        let body = self.issue(payload, session, client).await?;
+        self.remove_with_client::<JwtPayloadType>(&current.token_id, client)
+            .await
+            .map_err(AuthError::StorageError)?;
+        Ok(body)
+    }
+
+    /// 重新认证后提升会话的认证等级
+    /// 校验token后，以新的认证上下文与当前时间作为认证时间签发新的token_id，过期时间与会话信息保持不变，
+    /// 旧的token随之失效，避免提升前泄露的token也获得提升后的权限
+    pub async fn step_up<JwtPayloadType>(
+        &self,
+        token: &str,
+        context: AuthContext,
+        client: &ClientInfo,
+    ) -> Result<
+        AuthBody,
+        AuthError<
+            <JwtStorageProviderType as JwtStorageProvider>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+        >,
+    >
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        let mut payload = self.verify_with_client::<JwtPayloadType>(token, client).await?;
+        let current_token_id =
+            std::mem::replace(&mut payload.token_id, self.token_id_generator.generate());
+        let now = self.clock.now_millis();
+        payload.set_auth_context_at(context, now);
+        let session = self
+            .storage_provider
+            .load(&current_token_id)
+            .await
+            .map_err(AuthError::StorageError)?
+            .and_then(|saved| saved.session);
+        let mut body = self
+            .gen_auth_body(payload)
+            .await
+            .map_err(AuthError::DecodeError)?;
+        body.expire_in_ms = body.expire_ms - now;
+        body.session = session;
+        self.save(body.clone())
+            .await
+            .map_err(AuthError::StorageError)?;
+        self.remove_with_client::<JwtPayloadType>(&current_token_id, client)
+            .await
+            .map_err(AuthError::StorageError)?;
+        self.observer.on_step_up(&body.token_id, client);
+        Ok(body)
     }
 
     pub async fn remove<JwtPayloadType>(
@@ -137,30 +516,107 @@ where
     >
     where
         JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
-        JwtAuthProviderType: super::jwt_auth_provider::JwtAuthProvider<JwtPayload<JwtPayloadType>>,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        self.remove_with_client::<JwtPayloadType>(token_id, &ClientInfo::default()).await
+    }
+
+    /// 同[Self::remove]，同时将客户端信息提供给审计观察者
+    pub async fn remove_with_client<JwtPayloadType>(
+        &self,
+        token_id: &str,
+        client: &ClientInfo,
+    ) -> Result<
+        Option<AuthBody>,
+        <JwtStorageProviderType as JwtStorageProvider>::Error
+    >
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
     {
         let r = self.storage_provider.remove(token_id).await?;
+        if let (Some(revocations), Some(body)) = (&self.revocations, &r) {
+            revocations.revoke(token_id, body.expire_ms);
+        }
+        self.observer.on_remove(token_id, r.is_some(), client);
         Ok(r)
     }
 
-    fn gen_auth_body<JwtPayloadType>(
+    /// 生成并签名吊销列表，未设置[RevocationRegistry]时为空的列表
+    /// 校验方见`jwt_revocation_verifier::RevocationListVerifier`
+    pub async fn publish_revocation_list(
+        &self,
+    ) -> Result<String, <JwtAuthProviderType as JwtAsyncAuthProvider<RevocationList>>::Error>
+    where
+        JwtAuthProviderType: JwtAsyncAuthProvider<RevocationList>,
+    {
+        let now = self.clock.now_millis();
+        let list = match &self.revocations {
+            Some(revocations) => revocations.snapshot(self.issuer.clone(), now),
+            None => RevocationRegistry::new(RevocationFormat::Ids).snapshot(self.issuer.clone(), now),
+        };
+        self.auth_provider.encode(&list).await
+    }
+
+    /// 审计观察者
+    pub fn observer(&self) -> &dyn JwtAuditObserver {
+        self.observer.as_ref()
+    }
+
+    /// 时钟
+    pub fn clock(&self) -> &dyn JwtClock {
+        self.clock.as_ref()
+    }
+
+    async fn gen_auth_body<JwtPayloadType>(
         &self,
         payload: JwtPayload<JwtPayloadType>,
-    ) -> Result<AuthBody, <JwtAuthProviderType as JwtAuthProvider<JwtPayload<JwtPayloadType>>>::Error>
+    ) -> Result<AuthBody, <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error>
     where
         JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
-        JwtAuthProviderType: super::jwt_auth_provider::JwtAuthProvider<JwtPayload<JwtPayloadType>>,
+        JwtAuthProviderType: super::jwt_auth_provider::JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
     {
-        let token = self.auth_provider.encode(&payload)?;
+        let token = self.auth_provider.encode(&payload).await?;
         let ab = AuthBody {
             token_id: payload.token_id.clone(),
             token,
             expire_ms: payload.expire_ms,
             expire_in_ms: self.expire_in_ms,
+            session: None,
         };
         Ok(ab)
     }
 
+    /// 更新会话的最后活跃时间，距离上次更新不足[Self::with_last_seen_interval]时不写入
+    /// 只更新最后活跃时间，不会用校验时读取的数据覆盖并发的续期或提升认证等级，写入失败不影响校验结果
+    async fn touch(&self, saved: &AuthBody, now: i64) {
+        let Some(session) = saved.session.as_ref() else {
+            return;
+        };
+        if now - session.last_seen_ms < self.last_seen_interval.as_millis() as i64 {
+            return;
+        }
+        if self
+            .storage_provider
+            .update_last_seen(&saved.token_id, now)
+            .await
+            .is_err()
+        {
+            tracing::warn!(target: "jwt::session", "failed to update last seen");
+        }
+    }
+
+    /// 列出[subject]的所有未过期的会话
+    pub async fn list_sessions(
+        &self,
+        subject: &str,
+    ) -> Result<Vec<AuthBody>, <JwtStorageProviderType as JwtStorageProvider>::Error> {
+        let now = self.clock.now_millis();
+        let mut sessions = self.storage_provider.list_sessions(subject).await?;
+        sessions.retain(|body| now < body.expire_ms);
+        Ok(sessions)
+    }
+
     async fn save(
         &self,
         auth_body: AuthBody,
@@ -0,0 +1,151 @@
+/// 暴力破解防护
+/// 记录一段时间内的失败次数，超过阈值后在一段时间内拒绝该键的请求
+/// [JwtBearerProvider](crate::jwt_bear_provider::JwtBearerProvider)在校验失败时以客户端ip为键进行记录，
+/// 登录等场景可以自行以用户标识为键调用
+///
+use crate::jwt_clock::{JwtClock, SystemClock};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex, Once};
+use std::time::Duration;
+
+/// 限流的键
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    /// 客户端ip
+    Ip(String),
+    /// 用户标识，如用户名
+    Subject(String),
+}
+
+impl Display for RateLimitKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RateLimitKey::Ip(ip) => write!(f, "ip:{ip}"),
+            RateLimitKey::Subject(subject) => write!(f, "subject:{subject}"),
+        }
+    }
+}
+
+/// 无法获取客户端ip时不会按ip限流，只提示一次，避免刷屏
+pub(crate) fn warn_missing_client_ip() {
+    static WARNED: Once = Once::new();
+    WARNED.call_once(|| {
+        tracing::warn!(
+            "client ip unavailable, rate limiting by ip is skipped; serve with `into_make_service_with_connect_info`"
+        );
+    });
+}
+
+pub trait JwtRateLimiter: Send + Sync {
+    /// 检查是否允许请求，如果被锁定，返回还需要等待的时间
+    fn check(&self, key: &RateLimitKey) -> Result<(), Duration>;
+
+    /// 记录一次失败
+    fn record_failure(&self, key: &RateLimitKey);
+
+    /// 记录一次成功，一般会清除该键在窗口内的失败记录，但不会解除已有的锁定
+    /// 多个用户共享的键(如[RateLimitKey::Ip])不应调用，否则一次成功就能重置其他请求的失败次数
+    fn record_success(&self, key: &RateLimitKey);
+}
+
+#[derive(Default)]
+struct FailureRecord {
+    failures: VecDeque<u128>,
+    locked_until: Option<u128>,
+}
+
+/// 基于滑动窗口的限流器
+/// 在`window`时间内失败`max_failures`次后，锁定`lockout`时间
+pub struct SlidingWindowRateLimiter {
+    max_failures: usize,
+    window_ms: u128,
+    lockout_ms: u128,
+    records: Mutex<HashMap<RateLimitKey, FailureRecord>>,
+    clock: Arc<dyn JwtClock>,
+}
+
+impl SlidingWindowRateLimiter {
+    pub fn new(max_failures: usize, window: Duration, lockout: Duration) -> Self {
+        SlidingWindowRateLimiter {
+            max_failures: max_failures.max(1),
+            window_ms: window.as_millis(),
+            lockout_ms: lockout.as_millis(),
+            records: Mutex::new(HashMap::new()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 设置时钟，默认使用[SystemClock]
+    pub fn with_clock(mut self, clock: impl JwtClock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    fn now(&self) -> u128 {
+        self.clock.now_millis().max(0) as u128
+    }
+
+    /// 清理已经没有失败记录且未被锁定的键，可以定期调用以回收内存
+    pub fn purge_expired(&self) {
+        let now = self.now();
+        let mut records = self.records.lock().unwrap();
+        records.retain(|_, record| {
+            Self::prune(record, now, self.window_ms);
+            !record.failures.is_empty() || record.locked_until.is_some()
+        });
+    }
+
+    fn prune(record: &mut FailureRecord, now: u128, window_ms: u128) {
+        while let Some(at) = record.failures.front() {
+            if now.saturating_sub(*at) >= window_ms {
+                record.failures.pop_front();
+            } else {
+                break;
+            }
+        }
+        if let Some(until) = record.locked_until
+            && until <= now
+        {
+            record.locked_until = None;
+        }
+    }
+}
+
+impl JwtRateLimiter for SlidingWindowRateLimiter {
+    fn check(&self, key: &RateLimitKey) -> Result<(), Duration> {
+        let now = self.now();
+        let mut records = self.records.lock().unwrap();
+        if let Some(record) = records.get_mut(key) {
+            Self::prune(record, now, self.window_ms);
+            if let Some(until) = record.locked_until {
+                return Err(Duration::from_millis((until - now) as u64));
+            }
+        }
+        Ok(())
+    }
+
+    fn record_failure(&self, key: &RateLimitKey) {
+        let now = self.now();
+        let mut records = self.records.lock().unwrap();
+        let record = records.entry(key.clone()).or_default();
+        Self::prune(record, now, self.window_ms);
+        record.failures.push_back(now);
+        if record.failures.len() >= self.max_failures {
+            record.failures.clear();
+            record.locked_until = Some(now + self.lockout_ms);
+        }
+    }
+
+    fn record_success(&self, key: &RateLimitKey) {
+        let now = self.now();
+        let mut records = self.records.lock().unwrap();
+        if let Some(record) = records.get_mut(key) {
+            Self::prune(record, now, self.window_ms);
+            record.failures.clear();
+            if record.locked_until.is_none() {
+                records.remove(key);
+            }
+        }
+    }
+}
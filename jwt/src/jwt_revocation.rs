@@ -0,0 +1,217 @@
+/// 吊销列表
+/// 不访问存储的校验方(如其他服务)无法得知token已被删除，
+/// [JwtProvider](crate::jwt_provider::JwtProvider)设置[RevocationRegistry]后，删除授权时会记录被吊销的token_id，直到token过期，
+/// 并可以发布经过签名的吊销列表，见[JwtProvider::publish_revocation_list](crate::jwt_provider::JwtProvider::publish_revocation_list)
+/// 校验方通过`RevocationListVerifier`定期拉取吊销列表并拒绝被吊销的token
+///
+use crate::jwt_auth_provider::JwtAsyncAuthProvider;
+use crate::jwt_provider::JwtProvider;
+use crate::jwt_storage_provider::JwtStorageProvider;
+use axum::extract::State;
+use axum_core::response::{IntoResponse, Response};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// 发布吊销列表的默认路径
+pub const REVOCATION_LIST_PATH: &str = "/.well-known/revocation-list";
+
+/// 吊销列表的格式
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RevocationFormat {
+    /// 完整的token_id及其过期时间
+    Ids,
+    /// 布隆过滤器，体积小，但有[false_positive_rate]的概率误判未吊销的token
+    Bloom { false_positive_rate: f64 },
+}
+
+/// 签名后发布的吊销列表
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RevocationList {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    /// 生成时间(毫秒)，校验方以此忽略旧的列表
+    pub issued_ms: i64,
+    pub revoked: RevokedSet,
+}
+
+impl RevocationList {
+    /// [token_id]是否已被吊销，[now_ms]之前过期的token_id视为未吊销
+    pub fn contains(&self, token_id: &str, now_ms: i64) -> bool {
+        match &self.revoked {
+            RevokedSet::Ids { ids } => ids
+                .get(token_id)
+                .is_some_and(|expire_ms| now_ms < *expire_ms),
+            RevokedSet::Bloom(filter) => filter.contains(token_id),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RevokedSet {
+    /// token_id -> 过期时间(毫秒)
+    Ids { ids: HashMap<String, i64> },
+    Bloom(BloomFilter),
+}
+
+/// 布隆过滤器，使用sha256的结果做双重哈希
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct BloomFilter {
+    /// 位数组，base64url编码
+    #[serde(with = "base64_bytes")]
+    bits: Vec<u8>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /// 按预期元素数量与误判率创建
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        let n = expected_items.max(1) as f64;
+        let p = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-n * p.ln() / (ln2 * ln2)).ceil().max(8.0) as u64;
+        let num_hashes = ((num_bits as f64 / n) * ln2).round().max(1.0) as u32;
+        BloomFilter {
+            bits: vec![0; num_bits.div_ceil(8) as usize],
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    pub fn insert(&mut self, item: &str) {
+        for index in self.indexes(item) {
+            self.bits[(index / 8) as usize] |= 1 << (index % 8);
+        }
+    }
+
+    pub fn contains(&self, item: &str) -> bool {
+        self.indexes(item)
+            .all(|index| self.bits[(index / 8) as usize] & (1 << (index % 8)) != 0)
+    }
+
+    fn indexes(&self, item: &str) -> impl Iterator<Item = u64> + use<> {
+        let digest = Sha256::digest(item.as_bytes());
+        let h1 = u64::from_le_bytes(digest[..8].try_into().unwrap());
+        let h2 = u64::from_le_bytes(digest[8..16].try_into().unwrap());
+        let num_bits = self.num_bits;
+        (0..self.num_hashes as u64)
+            .map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
+
+mod base64_bytes {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        URL_SAFE_NO_PAD
+            .decode(s)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// 记录被吊销的token_id，直到其过期
+/// 可以Clone，各个副本共享同一份记录
+#[derive(Clone)]
+pub struct RevocationRegistry {
+    format: RevocationFormat,
+    revoked: Arc<RwLock<HashMap<String, i64>>>,
+}
+
+impl RevocationRegistry {
+    pub fn new(format: RevocationFormat) -> Self {
+        RevocationRegistry {
+            format,
+            revoked: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn format(&self) -> RevocationFormat {
+        self.format
+    }
+
+    /// 吊销[token_id]，记录保留到[expire_ms]
+    pub fn revoke(&self, token_id: impl Into<String>, expire_ms: i64) {
+        self.revoked
+            .write()
+            .unwrap()
+            .insert(token_id.into(), expire_ms);
+    }
+
+    pub fn is_revoked(&self, token_id: &str, now_ms: i64) -> bool {
+        self.revoked
+            .read()
+            .unwrap()
+            .get(token_id)
+            .is_some_and(|expire_ms| now_ms < *expire_ms)
+    }
+
+    /// 清理已过期的记录
+    pub fn purge_expired(&self, now_ms: i64) {
+        self.revoked
+            .write()
+            .unwrap()
+            .retain(|_, expire_ms| now_ms < *expire_ms);
+    }
+
+    /// 生成[now_ms]时的吊销列表，已过期的记录会被清理
+    pub fn snapshot(&self, issuer: Option<String>, now_ms: i64) -> RevocationList {
+        self.purge_expired(now_ms);
+        let revoked = self.revoked.read().unwrap();
+        let revoked = match self.format {
+            RevocationFormat::Ids => RevokedSet::Ids {
+                ids: revoked.clone(),
+            },
+            RevocationFormat::Bloom {
+                false_positive_rate,
+            } => {
+                let mut filter = BloomFilter::new(revoked.len(), false_positive_rate);
+                revoked.keys().for_each(|token_id| filter.insert(token_id));
+                RevokedSet::Bloom(filter)
+            }
+        };
+        RevocationList {
+            iss: issuer,
+            issued_ms: now_ms,
+            revoked,
+        }
+    }
+}
+
+/// 发布吊销列表的处理函数，返回签名后的吊销列表(`application/jwt`)
+/// 应用状态需要能够提取出`Arc<JwtProvider>`，如：
+/// `Router::new().route(REVOCATION_LIST_PATH, get(revocation_list_handler)).with_state(provider)`
+pub async fn revocation_list_handler<JwtAuthProviderType, JwtStorageProviderType>(
+    State(provider): State<Arc<JwtProvider<JwtAuthProviderType, JwtStorageProviderType>>>,
+) -> Response
+where
+    JwtStorageProviderType: JwtStorageProvider,
+    JwtAuthProviderType: JwtAsyncAuthProvider<RevocationList>,
+    <JwtAuthProviderType as JwtAsyncAuthProvider<RevocationList>>::Error: std::fmt::Debug,
+{
+    match provider.publish_revocation_list().await {
+        Ok(list) => (
+            [
+                (http::header::CONTENT_TYPE, "application/jwt"),
+                (http::header::CACHE_CONTROL, "no-cache"),
+            ],
+            list,
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("failed to publish revocation list: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -0,0 +1,241 @@
+/// 校验方使用的吊销列表
+/// 不访问存储的服务只持有校验密钥，通过[RevocationListVerifier]定期拉取签名的吊销列表，
+/// 并在校验token时拒绝被吊销的token，发布方见[crate::jwt_revocation]
+///
+use crate::jwt_clock::{JwtClock, SystemClock};
+use crate::jwt_payload::JwtPayload;
+use crate::jwt_revocation::RevocationList;
+use async_runtime::MaybeSend;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use scheduler::ScheduleId;
+use scheduler::scheduler::Scheduler;
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex, RwLock};
+use tracing::warn;
+use types::Duration;
+
+/// 吊销列表的来源，一般通过http请求发布方的[crate::jwt_revocation::REVOCATION_LIST_PATH]
+pub trait RevocationListSource: Send + Sync + 'static {
+    type Error: Debug + Send + 'static;
+
+    /// 获取签名后的吊销列表
+    fn fetch(&self) -> impl Future<Output = Result<String, Self::Error>> + MaybeSend;
+}
+
+#[derive(Debug)]
+pub enum RevocationListError<SourceError> {
+    /// 获取吊销列表失败
+    Source(SourceError),
+    /// 签名校验失败
+    DecodeError(jsonwebtoken::errors::Error),
+}
+
+/// 不访问存储的校验结果
+#[derive(Debug)]
+pub enum StatelessAuthError {
+    DecodeError(jsonwebtoken::errors::Error),
+    OutOfDate,
+    /// token已被吊销
+    Revoked,
+    /// 吊销列表尚未拉取或已超过[RevocationListVerifier::with_max_staleness]，无法确认token是否被吊销
+    RevocationListStale,
+}
+
+struct Inner<Source> {
+    source: Source,
+    decoding_key: DecodingKey,
+    validation: Validation,
+    list: RwLock<Option<RevocationList>>,
+    schedule: Mutex<ScheduleState>,
+}
+
+#[derive(Default)]
+struct ScheduleState {
+    /// 每次[RevocationListVerifier::start]与[RevocationListVerifier::stop]时递增，用于识别过时的定时任务
+    generation: u64,
+    current: Option<(Scheduler, ScheduleId)>,
+}
+
+impl ScheduleState {
+    /// 开始新的阶段，取消当前的定时任务
+    fn reset(&mut self) -> u64 {
+        self.generation += 1;
+        if let Some((mut scheduler, id)) = self.current.take()
+            && let Err(e) = scheduler.cancel(id)
+        {
+            warn!("取消吊销列表刷新任务失败: {e}");
+        }
+        self.generation
+    }
+}
+
+/// 定期刷新吊销列表，并据此校验token
+/// clone之间共享吊销列表与定时任务，时钟等设置在clone时复制
+pub struct RevocationListVerifier<Source> {
+    inner: Arc<Inner<Source>>,
+    clock: Arc<dyn JwtClock>,
+    max_staleness: Option<Duration>,
+}
+
+impl<Source> Clone for RevocationListVerifier<Source> {
+    fn clone(&self) -> Self {
+        RevocationListVerifier {
+            inner: self.inner.clone(),
+            clock: self.clock.clone(),
+            max_staleness: self.max_staleness,
+        }
+    }
+}
+
+impl<Source: RevocationListSource> RevocationListVerifier<Source> {
+    /// [decoding_key], [algorithm]需要与发布方的签名方式一致，
+    /// 如[HmacAuthProvider](crate::jwt_auth_provider::HmacAuthProvider)对应`DecodingKey::from_secret`与[Algorithm::HS256]
+    pub fn new(source: Source, decoding_key: DecodingKey, algorithm: Algorithm) -> Self {
+        let mut validation = Validation::new(algorithm);
+        validation.required_spec_claims.remove("exp");
+        validation.validate_exp = false;
+        RevocationListVerifier {
+            inner: Arc::new(Inner {
+                source,
+                decoding_key,
+                validation,
+                list: RwLock::new(None),
+                schedule: Mutex::new(ScheduleState::default()),
+            }),
+            clock: Arc::new(SystemClock),
+            max_staleness: None,
+        }
+    }
+
+    /// 设置时钟，用于判断token，吊销记录与吊销列表是否过期，默认使用[SystemClock]
+    pub fn with_clock(mut self, clock: impl JwtClock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// 设置吊销列表的最长有效时间，以发布方的生成时间计算，两端的时钟需要大致同步
+    /// 设置后，尚未拉取到列表或列表超过该时间仍未更新时拒绝所有token，
+    /// 不设置时这种情况下视为未吊销，发布方不可用期间被吊销的token仍然有效
+    pub fn with_max_staleness(mut self, max_staleness: Duration) -> Self {
+        self.max_staleness = Some(max_staleness);
+        self
+    }
+
+    /// 当前的吊销列表
+    pub fn list(&self) -> Option<RevocationList> {
+        self.inner.list.read().unwrap().clone()
+    }
+
+    /// 立即拉取吊销列表，比当前列表旧的列表会被忽略
+    pub async fn refresh(&self) -> Result<(), RevocationListError<Source::Error>> {
+        let token = self
+            .inner
+            .source
+            .fetch()
+            .await
+            .map_err(RevocationListError::Source)?;
+        let list = jsonwebtoken::decode::<RevocationList>(
+            &token,
+            &self.inner.decoding_key,
+            &self.inner.validation,
+        )
+        .map_err(RevocationListError::DecodeError)?
+        .claims;
+        let mut current = self.inner.list.write().unwrap();
+        if current
+            .as_ref()
+            .is_none_or(|current| current.issued_ms <= list.issued_ms)
+        {
+            *current = Some(list);
+        }
+        Ok(())
+    }
+
+    /// 立即拉取一次，之后每隔[interval]通过[Scheduler]刷新，重复调用会替换之前的定时任务
+    /// [Scheduler]返回任务id需要等待，因此在后台进行，期间调用[RevocationListVerifier::stop]或再次调用也会生效
+    pub fn start(&self, interval: Duration, scheduler: Scheduler) {
+        let generation = self.inner.schedule.lock().unwrap().reset();
+        self.spawn_refresh();
+        let verifier = self.clone();
+        async_runtime::spawn(async move {
+            let mut scheduler = scheduler;
+            let on_time = verifier.clone();
+            let ret = scheduler
+                .repeat(interval, move |_| {
+                    if on_time.inner.schedule.lock().unwrap().generation == generation {
+                        on_time.spawn_refresh();
+                    }
+                })
+                .await;
+            match ret {
+                Ok(id) => {
+                    let mut state = verifier.inner.schedule.lock().unwrap();
+                    if state.generation == generation {
+                        state.current = Some((scheduler, id));
+                    } else if let Err(e) = scheduler.cancel(id) {
+                        warn!("取消吊销列表刷新任务失败: {e}");
+                    }
+                }
+                Err(e) => warn!("安排吊销列表刷新任务失败: {e}"),
+            }
+        });
+    }
+
+    fn spawn_refresh(&self) {
+        let verifier = self.clone();
+        async_runtime::spawn(async move {
+            if let Err(e) = verifier.refresh().await {
+                warn!("拉取吊销列表失败: {e:?}");
+            }
+        });
+    }
+
+    /// 停止定期刷新
+    pub fn stop(&self) {
+        self.inner.schedule.lock().unwrap().reset();
+    }
+
+    /// [token_id]是否已被吊销
+    /// 吊销列表尚未拉取或已过旧时，设置了[Self::with_max_staleness]则视为已吊销，否则视为未吊销
+    pub fn is_revoked(&self, token_id: &str) -> bool {
+        self.check_revoked(token_id).is_err()
+    }
+
+    fn check_revoked(&self, token_id: &str) -> Result<(), StatelessAuthError> {
+        let now = self.clock.now_millis();
+        let list = self.inner.list.read().unwrap();
+        if let Some(max_staleness) = self.max_staleness {
+            let fresh = list
+                .as_ref()
+                .is_some_and(|list| now - list.issued_ms <= max_staleness.as_millis() as i64);
+            if !fresh {
+                warn!("吊销列表尚未拉取或已过旧，拒绝token: {token_id}");
+                return Err(StatelessAuthError::RevocationListStale);
+            }
+        }
+        if list.as_ref().is_some_and(|list| list.contains(token_id, now)) {
+            return Err(StatelessAuthError::Revoked);
+        }
+        Ok(())
+    }
+
+    /// 不访问存储校验token：检查签名，过期时间，以及是否已被吊销
+    pub fn verify<PayloadType: DeserializeOwned>(
+        &self,
+        token: &str,
+    ) -> Result<JwtPayload<PayloadType>, StatelessAuthError> {
+        let payload = jsonwebtoken::decode::<JwtPayload<PayloadType>>(
+            token,
+            &self.inner.decoding_key,
+            &self.inner.validation,
+        )
+        .map_err(StatelessAuthError::DecodeError)?
+        .claims;
+        if self.clock.now_millis() >= payload.expire_ms {
+            return Err(StatelessAuthError::OutOfDate);
+        }
+        self.check_revoked(&payload.token_id)?;
+        Ok(payload)
+    }
+}
@@ -0,0 +1,154 @@
+/// 敏感操作的二次认证(step-up)检查
+/// 要求会话的认证足够新(`auth_time`)且足够强(`acr`, `amr`)，否则以单独的错误拒绝，
+/// 客户端收到后应让用户重新认证，并通过[JwtProvider::step_up](crate::jwt_provider::JwtProvider::step_up)提升会话
+///
+use crate::jwt_clock::JwtClock;
+use crate::jwt_payload::JwtPayload;
+use crate::jwt_principal::PrincipalResolver;
+use axum_core::extract::FromRequestParts;
+use axum_core::response::{IntoResponse, Response};
+use http::StatusCode;
+use http::request::Parts;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// 二次认证的要求
+#[derive(Clone, Debug, Default)]
+pub struct StepUpRequirement {
+    /// 认证后的最长时间
+    pub max_age: Option<Duration>,
+    /// 可接受的认证等级，为空时不检查
+    pub acr_values: Vec<String>,
+    /// 必须使用过的认证方式
+    pub amr: Vec<String>,
+}
+
+impl StepUpRequirement {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn acr_values(mut self, acr_values: Vec<String>) -> Self {
+        self.acr_values = acr_values;
+        self
+    }
+
+    pub fn amr(mut self, amr: Vec<String>) -> Self {
+        self.amr = amr;
+        self
+    }
+
+    /// 以[clock]的当前时间检查载荷是否满足要求，[clock]应与签发token使用的时钟一致
+    pub fn check<PayloadType>(
+        &self,
+        payload: &JwtPayload<PayloadType>,
+        clock: &dyn JwtClock,
+    ) -> Result<(), StepUpError> {
+        self.check_at(payload, clock.now_millis())
+    }
+
+    /// 以[now_ms]作为当前时间，检查载荷是否满足要求
+    pub fn check_at<PayloadType>(
+        &self,
+        payload: &JwtPayload<PayloadType>,
+        now_ms: i64,
+    ) -> Result<(), StepUpError> {
+        if let Some(max_age) = self.max_age {
+            let now = now_ms / 1000;
+            match payload.auth_time {
+                Some(auth_time) if now - auth_time <= max_age.as_secs() as i64 => {}
+                _ => return Err(StepUpError::AuthTooOld),
+            }
+        }
+        if !self.acr_values.is_empty()
+            && !payload
+                .acr
+                .as_ref()
+                .is_some_and(|acr| self.acr_values.contains(acr))
+        {
+            return Err(StepUpError::AuthTooWeak);
+        }
+        if self.amr.iter().any(|amr| !payload.amr.contains(amr)) {
+            return Err(StepUpError::AuthTooWeak);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepUpError {
+    /// 距离上次认证太久
+    AuthTooOld,
+    /// 认证等级或认证方式不满足要求
+    AuthTooWeak,
+}
+
+impl IntoResponse for StepUpError {
+    fn into_response(self) -> Response {
+        let error_message = match self {
+            StepUpError::AuthTooOld => "authentication too old",
+            StepUpError::AuthTooWeak => "authentication too weak",
+        };
+        let body = axum::Json(serde_json::json!({
+            "error": error_message,
+            "step_up_required": true,
+        }));
+        // 见RFC 9470
+        (
+            StatusCode::UNAUTHORIZED,
+            [(
+                http::header::WWW_AUTHENTICATE,
+                "Bearer error=\"insufficient_user_authentication\"",
+            )],
+            body,
+        )
+            .into_response()
+    }
+}
+
+/// 为[StepUp]提供二次认证的要求，一般为每种敏感操作定义一个类型
+pub trait StepUpPolicy {
+    fn requirement() -> StepUpRequirement;
+}
+
+pub enum StepUpRejection<Rejection> {
+    /// 未通过身份校验
+    Unauthorized(Rejection),
+    StepUpRequired(StepUpError),
+}
+
+impl<Rejection: IntoResponse> IntoResponse for StepUpRejection<Rejection> {
+    fn into_response(self) -> Response {
+        match self {
+            StepUpRejection::Unauthorized(e) => e.into_response(),
+            StepUpRejection::StepUpRequired(e) => e.into_response(),
+        }
+    }
+}
+
+/// 要求满足[StepUpPolicy]的调用者，用法与[crate::jwt_principal::Principal]相同
+pub struct StepUp<PayloadType, Policy>(pub JwtPayload<PayloadType>, PhantomData<Policy>);
+
+impl<State, PayloadType, Policy> FromRequestParts<State> for StepUp<PayloadType, Policy>
+where
+    State: PrincipalResolver<PayloadType>,
+    Policy: StepUpPolicy,
+{
+    type Rejection = StepUpRejection<State::Rejection>;
+
+    async fn from_request_parts(parts: &mut Parts, state: &State) -> Result<Self, Self::Rejection> {
+        let payload = state
+            .resolve(parts)
+            .await
+            .map_err(StepUpRejection::Unauthorized)?;
+        Policy::requirement()
+            .check(&payload, state.clock())
+            .map_err(StepUpRejection::StepUpRequired)?;
+        Ok(StepUp(payload, PhantomData))
+    }
+}
@@ -19,4 +19,124 @@ pub trait JwtStorageProvider {
         &self,
         token_id: &str,
     ) -> impl Future<Output = Result<Option<super::jwt_provider::AuthBody>, Self::Error>>;
+
+    /// 列出[subject]的所有会话，即[SessionMetadata::subject](crate::jwt_payload::SessionMetadata::subject)相同的授权信息
+    /// 用于展示用户的登录设备，默认不支持，返回空列表
+    fn list_sessions(
+        &self,
+        subject: &str,
+    ) -> impl Future<Output = Result<Vec<super::jwt_provider::AuthBody>, Self::Error>> {
+        let _ = subject;
+        async { Ok(Vec::new()) }
+    }
+
+    /// 只更新会话的[SessionMetadata::last_seen_ms](crate::jwt_payload::SessionMetadata::last_seen_ms)，不覆盖其它字段
+    /// 默认重新加载最新的授权信息后保存，支持局部更新的存储应覆盖此方法以避免与并发的写入冲突
+    fn update_last_seen(
+        &self,
+        token_id: &str,
+        last_seen_ms: i64,
+    ) -> impl Future<Output = Result<(), Self::Error>> {
+        async move {
+            let Some(mut saved) = self.load(token_id).await? else {
+                return Ok(());
+            };
+            let Some(session) = saved.session.as_mut() else {
+                return Ok(());
+            };
+            session.last_seen_ms = last_seen_ms;
+            self.save(saved).await
+        }
+    }
+}
+
+/// 返回的Future都是`Send`的[JwtStorageProvider]，用于需要在多线程运行时中执行的场景，如[AuthRouter](crate::jwt_auth_router::AuthRouter)
+/// 实现此trait即自动实现[JwtStorageProvider]
+pub trait SendJwtStorageProvider: Sync {
+    type Error;
+
+    /// 见[JwtStorageProvider::save]
+    fn save(
+        &self,
+        auth_body: super::jwt_provider::AuthBody,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// 见[JwtStorageProvider::load]
+    fn load(
+        &self,
+        token_id: &str,
+    ) -> impl Future<Output = Result<Option<super::jwt_provider::AuthBody>, Self::Error>> + Send;
+
+    /// 见[JwtStorageProvider::remove]
+    fn remove(
+        &self,
+        token_id: &str,
+    ) -> impl Future<Output = Result<Option<super::jwt_provider::AuthBody>, Self::Error>> + Send;
+
+    /// 见[JwtStorageProvider::list_sessions]
+    fn list_sessions(
+        &self,
+        subject: &str,
+    ) -> impl Future<Output = Result<Vec<super::jwt_provider::AuthBody>, Self::Error>> + Send {
+        let _ = subject;
+        async { Ok(Vec::new()) }
+    }
+
+    /// 见[JwtStorageProvider::update_last_seen]
+    fn update_last_seen(
+        &self,
+        token_id: &str,
+        last_seen_ms: i64,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        async move {
+            let Some(mut saved) = SendJwtStorageProvider::load(self, token_id).await? else {
+                return Ok(());
+            };
+            let Some(session) = saved.session.as_mut() else {
+                return Ok(());
+            };
+            session.last_seen_ms = last_seen_ms;
+            SendJwtStorageProvider::save(self, saved).await
+        }
+    }
+}
+
+impl<T: SendJwtStorageProvider> JwtStorageProvider for T {
+    type Error = T::Error;
+
+    fn save(
+        &self,
+        auth_body: super::jwt_provider::AuthBody,
+    ) -> impl Future<Output = Result<(), Self::Error>> {
+        SendJwtStorageProvider::save(self, auth_body)
+    }
+
+    fn load(
+        &self,
+        token_id: &str,
+    ) -> impl Future<Output = Result<Option<super::jwt_provider::AuthBody>, Self::Error>> {
+        SendJwtStorageProvider::load(self, token_id)
+    }
+
+    fn remove(
+        &self,
+        token_id: &str,
+    ) -> impl Future<Output = Result<Option<super::jwt_provider::AuthBody>, Self::Error>> {
+        SendJwtStorageProvider::remove(self, token_id)
+    }
+
+    fn list_sessions(
+        &self,
+        subject: &str,
+    ) -> impl Future<Output = Result<Vec<super::jwt_provider::AuthBody>, Self::Error>> {
+        SendJwtStorageProvider::list_sessions(self, subject)
+    }
+
+    fn update_last_seen(
+        &self,
+        token_id: &str,
+        last_seen_ms: i64,
+    ) -> impl Future<Output = Result<(), Self::Error>> {
+        SendJwtStorageProvider::update_last_seen(self, token_id, last_seen_ms)
+    }
 }
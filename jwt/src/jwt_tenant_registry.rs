@@ -0,0 +1,197 @@
+/// 多租户的授权管理
+/// 每个租户拥有独立的[JwtBearerProvider](即独立的密钥，有效时长与存储)，
+/// 由[JwtTenantRegistry]根据请求选择对应的租户，对外提供与[JwtBearerProvider]一致的入口
+///
+use crate::jwt_auth_provider::JwtAsyncAuthProvider;
+use crate::jwt_bear_provider::{BearAuthError, JwtBearerProvider};
+use crate::jwt_payload::JwtPayload;
+use crate::jwt_provider::AuthBody;
+use crate::jwt_storage_provider::JwtStorageProvider;
+use axum_core::response::{IntoResponse, Response};
+use http::StatusCode;
+use http::request::Parts;
+use http_utils::utils::get_bear_token;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// 去掉host中的端口，IPv6地址需要带方括号才能带端口，没有方括号时整体作为地址
+fn strip_port(host: &str) -> &str {
+    if let Some(host) = host.strip_prefix('[') {
+        return host.split_once(']').map(|(ip, _)| ip).unwrap_or(host);
+    }
+    match host.rsplit_once(':') {
+        Some((name, _)) if !name.contains(':') => name,
+        _ => host,
+    }
+}
+
+/// 从请求中识别租户的方式
+#[derive(Clone, Debug)]
+pub enum TenantSelector {
+    /// token中的`iss`，租户的[JwtBearerProvider]需要通过`with_issuer`设置相同的签发者
+    Issuer,
+    /// 请求的host(不含端口)，IPv6地址不含方括号，如`[::1]:8080`为`::1`
+    Host,
+    /// 指定的请求头
+    Header(String),
+}
+
+pub enum TenantAuthError<StorageError, DecodeError> {
+    /// 无法从请求中识别租户
+    TenantMissing,
+    /// 未注册的租户
+    UnknownTenant(String),
+    BearAuthError(BearAuthError<StorageError, DecodeError>),
+}
+
+impl<StorageError: std::fmt::Debug, DecodeError: std::fmt::Debug> IntoResponse
+    for TenantAuthError<StorageError, DecodeError>
+{
+    fn into_response(self) -> Response {
+        let (status, error_message) = match self {
+            TenantAuthError::TenantMissing => (StatusCode::BAD_REQUEST, "tenant missing".to_string()),
+            TenantAuthError::UnknownTenant(tenant) => {
+                (StatusCode::UNAUTHORIZED, format!("unknown tenant: {tenant}"))
+            }
+            TenantAuthError::BearAuthError(e) => return e.into_response(),
+        };
+        let body = axum::Json(serde_json::json!({
+            "error": error_message,
+        }));
+        (status, body).into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct IssuerClaim {
+    iss: Option<String>,
+}
+
+pub struct JwtTenantRegistry<JwtAuthProviderType, JwtStorageProviderType> {
+    selector: TenantSelector,
+    tenants: RwLock<
+        HashMap<String, Arc<JwtBearerProvider<JwtAuthProviderType, JwtStorageProviderType>>>,
+    >,
+}
+
+impl<JwtAuthProviderType, JwtStorageProviderType>
+    JwtTenantRegistry<JwtAuthProviderType, JwtStorageProviderType>
+where
+    JwtStorageProviderType: JwtStorageProvider,
+{
+    pub fn new(selector: TenantSelector) -> Self {
+        JwtTenantRegistry {
+            selector,
+            tenants: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 注册租户，运行时也可以调用，已存在的同名租户会被替换
+    pub fn register(
+        &self,
+        tenant: impl Into<String>,
+        provider: JwtBearerProvider<JwtAuthProviderType, JwtStorageProviderType>,
+    ) {
+        let mut tenants = self.tenants.write().unwrap();
+        tenants.insert(tenant.into(), Arc::new(provider));
+    }
+
+    /// 移除租户
+    pub fn unregister(
+        &self,
+        tenant: &str,
+    ) -> Option<Arc<JwtBearerProvider<JwtAuthProviderType, JwtStorageProviderType>>> {
+        self.tenants.write().unwrap().remove(tenant)
+    }
+
+    /// 获取租户的授权提供者
+    pub fn get(
+        &self,
+        tenant: &str,
+    ) -> Option<Arc<JwtBearerProvider<JwtAuthProviderType, JwtStorageProviderType>>> {
+        self.tenants.read().unwrap().get(tenant).cloned()
+    }
+
+    /// 从请求中识别租户
+    pub async fn tenant_of(&self, parts: &mut Parts) -> Option<String> {
+        match &self.selector {
+            TenantSelector::Issuer => {
+                let token = get_bear_token(parts).await.ok()?;
+                // 此处只用于选择租户，签名由租户的提供者校验
+                jsonwebtoken::dangerous::insecure_decode::<IssuerClaim>(&token)
+                    .ok()?
+                    .claims
+                    .iss
+            }
+            TenantSelector::Host => {
+                let host = parts
+                    .headers
+                    .get(http::header::HOST)
+                    .and_then(|v| v.to_str().ok())
+                    .or_else(|| parts.uri.host())?;
+                Some(strip_port(host).to_string())
+            }
+            TenantSelector::Header(name) => parts
+                .headers
+                .get(name.as_str())
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string()),
+        }
+    }
+
+    /// 为指定租户授权
+    pub async fn authorize<JwtPayloadType>(
+        &self,
+        tenant: &str,
+        payload: JwtPayloadType,
+    ) -> Result<
+        AuthBody,
+        TenantAuthError<
+            <JwtStorageProviderType as JwtStorageProvider>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+        >,
+    >
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        let provider = self
+            .get(tenant)
+            .ok_or_else(|| TenantAuthError::UnknownTenant(tenant.to_string()))?;
+        provider
+            .authorize(payload)
+            .await
+            .map_err(|e| TenantAuthError::BearAuthError(BearAuthError::AuthError(e)))
+    }
+
+    /// 识别请求所属的租户，并使用该租户的提供者进行校验
+    /// 成功时返回租户与token对应的payload数据
+    pub async fn verify<JwtPayloadType>(
+        &self,
+        parts: &mut Parts,
+    ) -> Result<
+        (String, JwtPayload<JwtPayloadType>),
+        TenantAuthError<
+            <JwtStorageProviderType as JwtStorageProvider>::Error,
+            <JwtAuthProviderType as JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>>::Error,
+        >,
+    >
+    where
+        JwtPayloadType: serde::Serialize + std::cmp::PartialEq,
+        JwtAuthProviderType: JwtAsyncAuthProvider<JwtPayload<JwtPayloadType>>,
+    {
+        let tenant = self
+            .tenant_of(parts)
+            .await
+            .ok_or(TenantAuthError::TenantMissing)?;
+        let provider = self
+            .get(&tenant)
+            .ok_or_else(|| TenantAuthError::UnknownTenant(tenant.clone()))?;
+        let payload = provider
+            .verify::<JwtPayloadType>(parts)
+            .await
+            .map_err(TenantAuthError::BearAuthError)?;
+        Ok((tenant, payload))
+    }
+}
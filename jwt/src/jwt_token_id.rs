@@ -0,0 +1,42 @@
+/// token_id的生成
+/// [JwtProvider](crate::jwt_provider::JwtProvider)签发token时通过[TokenIdGenerator]生成token_id，
+/// 默认使用[Scru128TokenIdGenerator]，也可以使用[SharedUuidV7Generator]，使token_id带上节点id且按时间有序
+/// 任何`Fn() -> String`也可以作为生成器
+///
+use uuid_utils::SharedUuidV7Generator;
+
+pub trait TokenIdGenerator: Send + Sync {
+    fn generate(&self) -> String;
+}
+
+/// 使用scru128生成token_id
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Scru128TokenIdGenerator;
+
+impl TokenIdGenerator for Scru128TokenIdGenerator {
+    fn generate(&self) -> String {
+        scru128::new_string()
+    }
+}
+
+impl TokenIdGenerator for SharedUuidV7Generator {
+    fn generate(&self) -> String {
+        match self.now_uuid() {
+            Ok(uuid) => uuid.to_string(),
+            // 系统时间早于UNIX_EPOCH时无法带上节点id，退化为普通的uuid v7
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to generate token id with node id, fallback to uuid v7");
+                uuid::Uuid::now_v7().to_string()
+            }
+        }
+    }
+}
+
+impl<F> TokenIdGenerator for F
+where
+    F: Fn() -> String + Send + Sync,
+{
+    fn generate(&self) -> String {
+        self()
+    }
+}
@@ -0,0 +1,223 @@
+/// 测试工具，需要启用feature: test-util
+/// 提供可控的时钟，可检查的内存存储，固定的token_id，以及用于反例测试的过期/篡改token
+///
+use crate::jwt_auth_provider::JwtAuthProvider;
+use crate::jwt_clock::JwtClock;
+use crate::jwt_payload::{AuthBody, JwtPayload};
+use crate::jwt_storage_provider::SendJwtStorageProvider;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// 可控的时钟，clone后共享同一时间
+#[derive(Clone)]
+pub struct MockClock {
+    now_ms: Arc<AtomicI64>,
+}
+
+impl MockClock {
+    pub fn new(now_ms: i64) -> Self {
+        MockClock {
+            now_ms: Arc::new(AtomicI64::new(now_ms)),
+        }
+    }
+
+    /// 以当前系统时间为起点
+    pub fn from_system() -> Self {
+        Self::new(time::now_millis() as i64)
+    }
+
+    pub fn set(&self, now_ms: i64) {
+        self.now_ms.store(now_ms, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now_ms
+            .fetch_add(duration.as_millis() as i64, Ordering::SeqCst);
+    }
+}
+
+impl JwtClock for MockClock {
+    fn now_millis(&self) -> i64 {
+        self.now_ms.load(Ordering::SeqCst)
+    }
+}
+
+/// 内存存储，clone后共享同一份数据，便于交给[crate::jwt_provider::JwtProvider]后继续检查
+#[derive(Clone, Default)]
+pub struct MemoryStorageProvider {
+    auths: Arc<RwLock<HashMap<String, AuthBody>>>,
+}
+
+impl MemoryStorageProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.auths.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.auths.read().unwrap().is_empty()
+    }
+
+    pub fn contains(&self, token_id: &str) -> bool {
+        self.auths.read().unwrap().contains_key(token_id)
+    }
+
+    pub fn get(&self, token_id: &str) -> Option<AuthBody> {
+        self.auths.read().unwrap().get(token_id).cloned()
+    }
+
+    /// 所有已保存的token_id(已排序)
+    pub fn token_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.auths.read().unwrap().keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// 直接写入授权信息
+    pub fn insert(&self, auth_body: AuthBody) {
+        let mut auths = self.auths.write().unwrap();
+        auths.insert(auth_body.token_id.clone(), auth_body);
+    }
+
+    /// 将指定授权标记为已过期
+    pub fn expire(&self, token_id: &str) -> bool {
+        match self.auths.write().unwrap().get_mut(token_id) {
+            Some(auth) => {
+                auth.expire_ms = i64::MIN;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear(&self) {
+        self.auths.write().unwrap().clear();
+    }
+}
+
+impl SendJwtStorageProvider for MemoryStorageProvider {
+    type Error = Infallible;
+
+    async fn save(&self, auth_body: AuthBody) -> Result<(), Self::Error> {
+        self.insert(auth_body);
+        Ok(())
+    }
+
+    async fn load(&self, token_id: &str) -> Result<Option<AuthBody>, Self::Error> {
+        Ok(self.get(token_id))
+    }
+
+    async fn remove(&self, token_id: &str) -> Result<Option<AuthBody>, Self::Error> {
+        Ok(self.auths.write().unwrap().remove(token_id))
+    }
+
+    async fn list_sessions(&self, subject: &str) -> Result<Vec<AuthBody>, Self::Error> {
+        let mut sessions: Vec<AuthBody> = self
+            .auths
+            .read()
+            .unwrap()
+            .values()
+            .filter(|body| {
+                body.session
+                    .as_ref()
+                    .is_some_and(|session| session.subject.as_deref() == Some(subject))
+            })
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|body| body.session.as_ref().map(|session| session.created_ms));
+        Ok(sessions)
+    }
+
+    async fn update_last_seen(&self, token_id: &str, last_seen_ms: i64) -> Result<(), Self::Error> {
+        if let Some(session) = self
+            .auths
+            .write()
+            .unwrap()
+            .get_mut(token_id)
+            .and_then(|body| body.session.as_mut())
+        {
+            session.last_seen_ms = last_seen_ms;
+        }
+        Ok(())
+    }
+}
+
+/// 依次生成`{prefix}-1`, `{prefix}-2`...的token_id
+pub fn sequential_token_ids(prefix: impl Into<String>) -> impl Fn() -> String + Send + Sync + 'static {
+    let prefix = prefix.into();
+    let counter = AtomicU64::new(0);
+    move || {
+        let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        format!("{prefix}-{n}")
+    }
+}
+
+/// 签发一个按[clock]已过期的token，并保存到[storage]中
+/// [clock]应与校验token的[JwtProvider](crate::jwt_provider::JwtProvider)使用同一个时钟
+pub fn forge_expired_token<PayloadType, JwtAuthProviderType>(
+    auth_provider: &JwtAuthProviderType,
+    storage: &MemoryStorageProvider,
+    clock: &dyn JwtClock,
+    token_id: impl Into<String>,
+    payload: PayloadType,
+) -> Result<AuthBody, JwtAuthProviderType::Error>
+where
+    PayloadType: serde::Serialize,
+    JwtAuthProviderType: JwtAuthProvider<JwtPayload<PayloadType>>,
+{
+    let now = clock.now_millis();
+    let payload = JwtPayload::new_at(token_id.into(), payload, now - 2000, 1000);
+    let token = auth_provider.encode(&payload)?;
+    let auth_body = AuthBody {
+        token_id: payload.token_id.clone(),
+        token,
+        expire_ms: payload.expire_ms,
+        expire_in_ms: 1000,
+        session: None,
+    };
+    storage.insert(auth_body.clone());
+    Ok(auth_body)
+}
+
+/// 修改token中的载荷，保留原有签名，因此得到的token无法通过签名校验
+/// token格式不正确时返回[None]
+pub fn tamper_claims(token: &str, f: impl FnOnce(&mut serde_json::Value)) -> Option<String> {
+    let mut segments = token.split('.');
+    let (Some(header), Some(claims), Some(signature), None) = (
+        segments.next(),
+        segments.next(),
+        segments.next(),
+        segments.next(),
+    ) else {
+        return None;
+    };
+    let claims = URL_SAFE_NO_PAD.decode(claims).ok()?;
+    let mut claims: serde_json::Value = serde_json::from_slice(&claims).ok()?;
+    f(&mut claims);
+    let claims = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).ok()?);
+    Some(format!("{header}.{claims}.{signature}"))
+}
+
+/// 破坏token的签名(修改签名的第一个字符)
+pub fn tamper_signature(token: &str) -> String {
+    match token.rfind('.') {
+        Some(idx) if idx + 1 < token.len() => {
+            let (head, signature) = token.split_at(idx + 1);
+            let mut chars = signature.chars();
+            let first = match chars.next() {
+                Some('A') => 'B',
+                _ => 'A',
+            };
+            format!("{head}{first}{}", chars.as_str())
+        }
+        _ => format!("{token}A"),
+    }
+}
@@ -3,4 +3,46 @@ pub use command::ScheduleId;
 
 mod command;
 mod scheduled_task;
-mod error;
\ No newline at end of file
+mod error;
+
+#[cfg(all(test, not(feature = "dioxus")))]
+mod test {
+    use crate::scheduler::Scheduler;
+    use std::time::Duration;
+
+    #[test]
+    fn test_due_task_with_pending_task() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()
+            .unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        // 调度器关闭后会丢弃未执行的任务，需要保持到结束
+        let _scheduler = runtime.block_on(async {
+            let mut scheduler = Scheduler::new();
+            let later = tx.clone();
+            scheduler
+                .once_after(Duration::from_millis(300), move |_| {
+                    let _ = later.send("later");
+                })
+                .await
+                .unwrap();
+            scheduler
+                .once_after(Duration::from_millis(10), move |_| {
+                    let _ = tx.send("soon");
+                })
+                .await
+                .unwrap();
+            scheduler
+        });
+
+        // 在运行时之外等待，调度循环空转时不会影响超时
+        let received: Vec<_> = std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(2)).ok())
+            .take(2)
+            .collect();
+        runtime.shutdown_background();
+        // 到期的任务执行后，还没到期的任务不能让调度循环空转
+        assert_eq!(vec!["soon", "later"], received);
+    }
+}
@@ -11,10 +11,17 @@ use types::{Duration, Instant};
 use tokio::spawn;
 use tracing::{error, warn};
 
+#[derive(Clone)]
 pub struct Scheduler {
     tx: UnboundedSender<Command>,
 }
 
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Scheduler {
     pub fn new() -> Self {
         let (tx, rx) = unbounded();
@@ -105,7 +112,7 @@ impl Scheduler {
                                         let task = ScheduledTask::new(id, spec);
                                         tasks.push(task);
                                         next_id += 1;
-                                        if let Err(_) = notifier.send(id) {
+                                        if notifier.send(id).is_err() {
                                             error!("发送[ScheduleId]失败: 接收方提前关闭");
                                         }
                                     }
@@ -120,12 +127,14 @@ impl Scheduler {
                         let now = Instant::now();
                         // 取出可用任务，并检查是否超时
                         while let Some(when) = peek_valid_task_deadline(&mut deleting, &mut tasks) {
-                            if now >= when {
-                                if let Some(task) = tasks.pop() {
-                                    if let Some(new_task) = task.execute() {
-                                        tasks.push(new_task);
-                                    }
-                                }
+                            // 剩下的任务都还没到期，回到外层循环等待
+                            if now < when {
+                                break;
+                            }
+                            if let Some(task) = tasks.pop()
+                                && let Some(new_task) = task.execute()
+                            {
+                                tasks.push(new_task);
                             }
                         }
 
@@ -154,13 +163,11 @@ impl Scheduler {
         #[cfg(feature = "dioxus")]
         {
             spawn(f);
-            return;
         }
 
         #[cfg(all(not(target_arch = "wasm32"), not(feature = "dioxus")))]
         {
             spawn(f);
-            return;
         }
 
         // wasm,但是又没有启用dioxus,当前不会真正启动任务
@@ -1,4 +1,5 @@
 #[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::module_inception)]
 pub mod time {
     use std::time;
 
@@ -9,6 +10,7 @@ pub mod time {
 }
 
 #[cfg(target_arch = "wasm32")]
+#[allow(clippy::module_inception)]
 pub mod time {
     pub type Instant = web_time::Instant;
     pub type SystemTime = web_time::SystemTime;
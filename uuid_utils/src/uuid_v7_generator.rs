@@ -33,9 +33,9 @@ impl UuidV7Generator {
 
     pub fn new(node_id: u16, counter_bits: u8) -> Self {
         Self {
-            node_id: node_id,
+            node_id,
             counter: 0,
-            counter_bits: counter_bits,
+            counter_bits,
         }
     }
 